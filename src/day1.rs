@@ -1,77 +1,645 @@
+use num::{BigUint, CheckedAdd, Integer, Zero};
+use std::fmt;
+
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd)]
-pub struct FuelRequirement {
-    fuel_for_modules: u64,
-    fuel_for_modules_and_fuel: u64,
+pub struct FuelRequirement<T = u64> {
+    fuel_for_modules: T,
+    fuel_for_modules_and_fuel: T,
+}
+
+impl<T> FuelRequirement<T> {
+    pub fn fuel_for_modules(&self) -> &T {
+        &self.fuel_for_modules
+    }
+
+    pub fn fuel_for_modules_and_fuel(&self) -> &T {
+        &self.fuel_for_modules_and_fuel
+    }
 }
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd)]
-pub struct Module {
-    pub mass: u32,
+pub struct Module<M = u32> {
+    pub mass: M,
+}
+
+/// An unsigned integer type that masses and fuel can be measured in, such as `u32`, `u128` or
+/// `num::BigUint`
+pub trait FuelInt: Integer + CheckedAdd + Clone + From<u8> {
+    /// The type fuel totals are summed in, which is at least as wide as `u64` so that totals
+    /// over many modules don't overflow as soon as the masses' own type would
+    type Total: Integer + CheckedAdd + Clone + From<Self>;
+}
+
+macro_rules! impl_fuel_int {
+    ($($t:ty => $total:ty),*) => {
+        $(impl FuelInt for $t {
+            type Total = $total;
+        })*
+    };
 }
 
-fn mass_to_fuel(mass: u32) -> u32 {
-    if mass / 3 <= 2 {
-        0
+impl_fuel_int!(u8 => u64, u16 => u64, u32 => u64, u64 => u128, u128 => u128, BigUint => BigUint);
+
+fn mass_to_fuel<T: FuelInt>(mass: T) -> T {
+    let third = mass / T::from(3);
+    if third <= T::from(2) {
+        T::zero()
     } else {
-        mass / 3 - 2
+        third - T::from(2)
+    }
+}
+
+/// Computes the fuel required to lift a mass, measured in `T`.
+///
+/// Implementations must require strictly less fuel than the mass being lifted (and no fuel for
+/// no mass), so that the cascade of fuel for fuel always terminates.
+pub trait FuelModel<T = u32> {
+    fn fuel_for_mass(&self, mass: T) -> T;
+}
+
+/// The rocket equation from the puzzle: divide by three, round down, and subtract two.  This
+/// works for any integer width.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+pub struct RocketEquation;
+
+impl<T: FuelInt> FuelModel<T> for RocketEquation {
+    fn fuel_for_mass(&self, mass: T) -> T {
+        mass_to_fuel(mass)
+    }
+}
+
+/// Divide by `divisor`, round down, and subtract `offset`, with a floor at zero
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct DivisorOffsetModel {
+    divisor: u32,
+    offset: u32,
+}
+
+impl DivisorOffsetModel {
+    pub fn new(divisor: u32, offset: u32) -> Result<DivisorOffsetModel, String> {
+        if divisor == 0 || (divisor == 1 && offset == 0) {
+            Err(format!(
+                "Divisor {} and offset {} don't reduce the mass",
+                divisor, offset
+            ))
+        } else {
+            Ok(DivisorOffsetModel { divisor, offset })
+        }
+    }
+}
+
+impl FuelModel for DivisorOffsetModel {
+    fn fuel_for_mass(&self, mass: u32) -> u32 {
+        (mass / self.divisor).saturating_sub(self.offset)
+    }
+}
+
+/// Looks up the fuel in a table of `(min_mass, fuel)` steps.  A mass requires the fuel of the
+/// step with the largest `min_mass` not above it, or no fuel if it's below every step.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct TableModel {
+    steps: Vec<(u32, u32)>,
+}
+
+impl TableModel {
+    pub fn new(mut steps: Vec<(u32, u32)>) -> Result<TableModel, String> {
+        steps.sort();
+        for pair in steps.windows(2) {
+            if pair[0].0 == pair[1].0 {
+                return Err(format!("Duplicate step for mass {}", pair[0].0));
+            }
+        }
+        if let Some(&(min_mass, fuel)) = steps.iter().find(|(min_mass, fuel)| fuel >= min_mass) {
+            return Err(format!(
+                "Step for mass {} requires {} fuel, which is not less than the mass",
+                min_mass, fuel
+            ));
+        }
+        Ok(TableModel { steps })
+    }
+}
+
+impl FuelModel for TableModel {
+    fn fuel_for_mass(&self, mass: u32) -> u32 {
+        match self
+            .steps
+            .binary_search_by_key(&mass, |&(min_mass, _)| min_mass)
+        {
+            Ok(idx) => self.steps[idx].1,
+            Err(0) => 0,
+            Err(idx) => self.steps[idx - 1].1,
+        }
+    }
+}
+
+/// A continuous model based on the Tsiolkovsky rocket equation.  Lifting a dry mass `m` through
+/// a velocity change `delta_v` requires `m * (exp(delta_v / exhaust_velocity) - 1)` fuel, rounded
+/// down.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct TsiolkovskyModel {
+    /// The fuel required per unit of mass
+    fuel_ratio: f64,
+}
+
+impl TsiolkovskyModel {
+    /// Fails unless the fuel ratio is less than one (`delta_v < exhaust_velocity * ln 2`),
+    /// since otherwise the fuel for fuel would never converge.
+    pub fn new(delta_v: f64, exhaust_velocity: f64) -> Result<TsiolkovskyModel, String> {
+        let fuel_ratio = (delta_v / exhaust_velocity).exp_m1();
+        if !(0.0..1.0).contains(&fuel_ratio) {
+            Err(format!(
+                "Delta-v {} with exhaust velocity {} requires a fuel ratio of {}",
+                delta_v, exhaust_velocity, fuel_ratio
+            ))
+        } else {
+            Ok(TsiolkovskyModel { fuel_ratio })
+        }
+    }
+}
+
+impl FuelModel for TsiolkovskyModel {
+    fn fuel_for_mass(&self, mass: u32) -> u32 {
+        (mass as f64 * self.fuel_ratio).floor() as u32
     }
 }
 
 /// Returns the amount of fuel required for just the modules,
-/// and the total amount of fuel for both the modules and additional fuel
-pub fn compute_fuel_requirement(modules: &[Module]) -> FuelRequirement {
-    let mut fuel: u64 = 0;
-    let mut extra_fuel_agg: u64 = 0;
-    for m in modules {
-        let mut new_fuel = mass_to_fuel(m.mass);
-        fuel += new_fuel as u64;
-        while new_fuel > 0 {
-            new_fuel = mass_to_fuel(new_fuel);
-            extra_fuel_agg += new_fuel as u64;
+/// and the total amount of fuel for both the modules and additional fuel.
+///
+/// Modules can be of any integer width, and are consumed one at a time, so they can be streamed
+/// rather than collected first.  The totals are summed in `T::Total`, which is at least as wide
+/// as `u64`, and an error is returned if either total overflows.
+pub fn compute_fuel_requirement<T, F, I>(
+    modules: I,
+    model: &F,
+) -> Result<FuelRequirement<T::Total>, String>
+where
+    T: FuelInt,
+    F: FuelModel<T> + ?Sized,
+    I: IntoIterator<Item = Module<T>>,
+{
+    let mut fuel = T::Total::zero();
+    let mut total = T::Total::zero();
+    for (idx, m) in modules.into_iter().enumerate() {
+        let overflow = || format!("Fuel total overflowed at module {}", idx);
+        let mut new_fuel = model.fuel_for_mass(m.mass);
+        fuel = fuel
+            .checked_add(&new_fuel.clone().into())
+            .ok_or_else(overflow)?;
+        while !new_fuel.is_zero() {
+            total = total
+                .checked_add(&new_fuel.clone().into())
+                .ok_or_else(overflow)?;
+            new_fuel = model.fuel_for_mass(new_fuel);
         }
     }
-    FuelRequirement {
+    Ok(FuelRequirement {
         fuel_for_modules: fuel,
-        fuel_for_modules_and_fuel: fuel + extra_fuel_agg,
+        fuel_for_modules_and_fuel: total,
+    })
+}
+
+/// Returns the full fuel cascade for a module: its mass, the fuel for that mass, the fuel for
+/// that fuel, and so on until no more fuel is required.
+///
+/// For example, a module of mass 1969 has the cascade 1969, 654, 216, 70, 21, 5.
+pub fn fuel_cascade<F: FuelModel + ?Sized>(mass: u32, model: &F) -> Vec<u32> {
+    let mut cascade = vec![mass];
+    let mut fuel = model.fuel_for_mass(mass);
+    while fuel > 0 {
+        cascade.push(fuel);
+        fuel = model.fuel_for_mass(fuel);
+    }
+    cascade
+}
+
+/// The fuel required by a single module
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ModuleFuel {
+    /// Position of the module in the list passed to `compute_fuel_breakdown`
+    pub index: usize,
+    pub module: Module,
+
+    /// Fuel for the module's mass alone
+    pub direct_fuel: u32,
+
+    /// The fuel required for the direct fuel, then for that fuel, and so on
+    pub fuel_for_fuel: Vec<u32>,
+}
+
+impl ModuleFuel {
+    /// Total fuel for the module, including fuel for fuel
+    pub fn total_fuel(&self) -> u64 {
+        self.direct_fuel as u64 + self.fuel_for_fuel.iter().map(|&f| f as u64).sum::<u64>()
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct FuelBreakdown {
+    pub modules: Vec<ModuleFuel>,
+}
+
+/// Computes the fuel required by each module individually
+pub fn compute_fuel_breakdown<F: FuelModel + ?Sized>(
+    modules: &[Module],
+    model: &F,
+) -> FuelBreakdown {
+    let modules = modules
+        .iter()
+        .enumerate()
+        .map(|(index, m)| {
+            let cascade = fuel_cascade(m.mass, model);
+            ModuleFuel {
+                index,
+                module: *m,
+                direct_fuel: cascade.get(1).cloned().unwrap_or(0),
+                fuel_for_fuel: cascade.iter().skip(2).cloned().collect(),
+            }
+        })
+        .collect();
+    FuelBreakdown { modules }
+}
+
+impl FuelBreakdown {
+    /// The totals over all modules, as returned by `compute_fuel_requirement`
+    pub fn total(&self) -> FuelRequirement {
+        FuelRequirement {
+            fuel_for_modules: self.modules.iter().map(|m| m.direct_fuel as u64).sum(),
+            fuel_for_modules_and_fuel: self.modules.iter().map(ModuleFuel::total_fuel).sum(),
+        }
+    }
+
+    /// Sorts the modules so that the largest total fuel comes first
+    pub fn sort_by_contribution(&mut self) {
+        self.modules.sort_by(|a, b| {
+            b.total_fuel()
+                .cmp(&a.total_fuel())
+                .then(a.index.cmp(&b.index))
+        });
+    }
+
+    /// Keeps only the modules whose total fuel is at least `min_fuel`
+    pub fn filter_min_contribution(&mut self, min_fuel: u64) {
+        self.modules.retain(|m| m.total_fuel() >= min_fuel);
+    }
+
+    /// Renders the breakdown as CSV, with a header row
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("index,mass,direct_fuel,fuel_for_fuel,total_fuel\n");
+        for m in &self.modules {
+            csv += &format!(
+                "{},{},{},{},{}\n",
+                m.index,
+                m.module.mass,
+                m.direct_fuel,
+                m.total_fuel() - m.direct_fuel as u64,
+                m.total_fuel()
+            );
+        }
+        csv
+    }
+}
+
+impl fmt::Display for FuelBreakdown {
+    /// Renders the breakdown as a table, followed by the totals
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "{:>5} {:>10} {:>10} {:>13} {:>10}",
+            "index", "mass", "direct", "fuel for fuel", "total"
+        )?;
+        for m in &self.modules {
+            writeln!(
+                f,
+                "{:>5} {:>10} {:>10} {:>13} {:>10}",
+                m.index,
+                m.module.mass,
+                m.direct_fuel,
+                m.total_fuel() - m.direct_fuel as u64,
+                m.total_fuel()
+            )?;
+        }
+        let total = self.total();
+        write!(
+            f,
+            "{:>5} {:>10} {:>10} {:>13} {:>10}",
+            "total",
+            "",
+            total.fuel_for_modules,
+            total.fuel_for_modules_and_fuel - total.fuel_for_modules,
+            total.fuel_for_modules_and_fuel
+        )
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use num::BigUint;
+    use proptest::prelude::*;
 
     #[test]
     fn test_mass_to_fuel() {
-        assert_eq!(mass_to_fuel(12), 2);
-        assert_eq!(mass_to_fuel(14), 2);
-        assert_eq!(mass_to_fuel(1969), 654);
-        assert_eq!(mass_to_fuel(100756), 33583);
+        assert_eq!(mass_to_fuel(12_u32), 2);
+        assert_eq!(mass_to_fuel(14_u32), 2);
+        assert_eq!(mass_to_fuel(1969_u32), 654);
+        assert_eq!(mass_to_fuel(100756_u32), 33583);
     }
 
     #[test]
     fn test_compute_fuel_requirement() {
         assert_eq!(
             2,
-            compute_fuel_requirement(&[Module { mass: 14 }]).fuel_for_modules_and_fuel
+            compute_fuel_requirement([Module { mass: 14_u32 }], &RocketEquation)
+                .unwrap()
+                .fuel_for_modules_and_fuel
         );
         assert_eq!(
             966,
-            compute_fuel_requirement(&[Module { mass: 1969 }]).fuel_for_modules_and_fuel
+            compute_fuel_requirement([Module { mass: 1969_u32 }], &RocketEquation)
+                .unwrap()
+                .fuel_for_modules_and_fuel
         );
         assert_eq!(
             50346,
-            compute_fuel_requirement(&[Module { mass: 100756 }]).fuel_for_modules_and_fuel
+            compute_fuel_requirement([Module { mass: 100756_u32 }], &RocketEquation)
+                .unwrap()
+                .fuel_for_modules_and_fuel
         );
     }
 
     #[test]
     fn test_negative_fuel_is_zero() {
-        assert_eq!(mass_to_fuel(1), 0);
+        assert_eq!(mass_to_fuel(1_u32), 0);
+    }
+
+    #[test]
+    fn test_fuel_cascade() {
+        assert_eq!(
+            vec![1969, 654, 216, 70, 21, 5],
+            fuel_cascade(1969, &RocketEquation)
+        );
+        assert_eq!(vec![14, 2], fuel_cascade(14, &RocketEquation));
+        assert_eq!(vec![8], fuel_cascade(8, &RocketEquation));
+    }
+
+    #[test]
+    fn test_fuel_breakdown() {
+        let modules = [
+            Module { mass: 14 },
+            Module { mass: 100756 },
+            Module { mass: 1969 },
+        ];
+        let mut breakdown = compute_fuel_breakdown(&modules, &RocketEquation);
+        assert_eq!(654, breakdown.modules[2].direct_fuel);
+        assert_eq!(vec![216, 70, 21, 5], breakdown.modules[2].fuel_for_fuel);
+        assert_eq!(966, breakdown.modules[2].total_fuel());
+        assert_eq!(
+            Ok(breakdown.total()),
+            compute_fuel_requirement(modules, &RocketEquation)
+        );
+
+        breakdown.sort_by_contribution();
+        let order: Vec<_> = breakdown.modules.iter().map(|m| m.index).collect();
+        assert_eq!(vec![1, 2, 0], order);
+
+        breakdown.filter_min_contribution(966);
+        assert_eq!(2, breakdown.modules.len());
+        assert_eq!(
+            "index,mass,direct_fuel,fuel_for_fuel,total_fuel\n\
+             1,100756,33583,16763,50346\n\
+             2,1969,654,312,966\n",
+            breakdown.to_csv()
+        );
+    }
+
+    #[test]
+    fn test_fuel_breakdown_table() {
+        let breakdown = compute_fuel_breakdown(&[Module { mass: 1969 }], &RocketEquation);
+        assert_eq!(
+            "index       mass     direct fuel for fuel      total\n\
+             \x20   0       1969        654           312        966\n\
+             total                   654           312        966",
+            format!("{}", breakdown)
+        );
+    }
+
+    #[test]
+    fn test_divisor_offset_model() {
+        let model = DivisorOffsetModel::new(3, 2).unwrap();
+        assert_eq!(
+            compute_fuel_requirement(get_modules(), &RocketEquation),
+            compute_fuel_requirement(get_modules(), &model)
+        );
+        assert_eq!(
+            vec![100, 50, 25, 12, 6, 3, 1],
+            fuel_cascade(100, &DivisorOffsetModel::new(2, 0).unwrap())
+        );
+
+        assert!(DivisorOffsetModel::new(0, 2).is_err());
+        assert!(DivisorOffsetModel::new(1, 0).is_err());
+        assert!(DivisorOffsetModel::new(1, 1).is_ok());
+    }
+
+    #[test]
+    fn test_table_model() {
+        let model = TableModel::new(vec![(1000, 300), (10, 1), (100, 30)]).unwrap();
+        assert_eq!(0, model.fuel_for_mass(9));
+        assert_eq!(1, model.fuel_for_mass(10));
+        assert_eq!(1, model.fuel_for_mass(99));
+        assert_eq!(30, model.fuel_for_mass(100));
+        assert_eq!(300, model.fuel_for_mass(5000));
+        assert_eq!(vec![5000, 300, 30, 1], fuel_cascade(5000, &model));
+
+        assert!(TableModel::new(vec![(10, 10)]).is_err());
+        assert!(TableModel::new(vec![(10, 1), (10, 2)]).is_err());
+    }
+
+    #[test]
+    fn test_tsiolkovsky_model() {
+        // a fuel ratio of exactly one half
+        let model = TsiolkovskyModel::new(1.5_f64.ln(), 1.0).unwrap();
+        assert_eq!(vec![100, 50, 25, 12, 6, 3, 1], fuel_cascade(100, &model));
+
+        assert!(TsiolkovskyModel::new(2.0_f64.ln(), 1.0).is_err());
+        assert!(TsiolkovskyModel::new(-1.0, 1.0).is_err());
+        assert!(TsiolkovskyModel::new(1.0, 0.0).is_err());
+    }
+
+    #[test]
+    fn test_dyn_fuel_model() {
+        let models: Vec<Box<dyn FuelModel>> = vec![
+            Box::new(RocketEquation),
+            Box::new(DivisorOffsetModel::new(3, 2).unwrap()),
+        ];
+        for model in &models {
+            assert_eq!(
+                966,
+                compute_fuel_requirement([Module { mass: 1969 }], model.as_ref())
+                    .unwrap()
+                    .fuel_for_modules_and_fuel
+            );
+        }
+    }
+
+    #[test]
+    fn test_wide_fuel_requirement() {
+        let expected = compute_fuel_requirement(get_modules(), &RocketEquation).unwrap();
+        let wide = |m: &Module| Module {
+            mass: m.mass as u128,
+        };
+        let actual =
+            compute_fuel_requirement(get_modules().iter().map(wide), &RocketEquation).unwrap();
+        assert_eq!(
+            (
+                *expected.fuel_for_modules() as u128,
+                *expected.fuel_for_modules_and_fuel() as u128
+            ),
+            (
+                *actual.fuel_for_modules(),
+                *actual.fuel_for_modules_and_fuel()
+            )
+        );
+
+        let big = compute_fuel_requirement(
+            get_modules().iter().map(|m| Module {
+                mass: BigUint::from(m.mass),
+            }),
+            &RocketEquation,
+        )
+        .unwrap();
+        assert_eq!(
+            BigUint::from(*expected.fuel_for_modules_and_fuel()),
+            *big.fuel_for_modules_and_fuel()
+        );
+    }
+
+    #[test]
+    fn test_big_fuel_requirement() {
+        // a mass of 3^100 needs 3^99 - 2 fuel directly
+        let mass = num::pow(BigUint::from(3_u8), 100);
+        let fuel =
+            compute_fuel_requirement(vec![Module { mass: mass.clone() }], &RocketEquation).unwrap();
+        assert_eq!(
+            num::pow(BigUint::from(3_u8), 99) - BigUint::from(2_u8),
+            *fuel.fuel_for_modules()
+        );
+        assert!(*fuel.fuel_for_modules_and_fuel() > *fuel.fuel_for_modules());
+        assert!(*fuel.fuel_for_modules_and_fuel() < mass);
+    }
+
+    #[test]
+    fn test_fuel_totals_are_wider_than_masses() {
+        // the fuel for these would overflow a u32 at the third module, but totals are in u64
+        let heavy = || std::iter::repeat_n(Module { mass: u32::MAX }, 10);
+        let fuel = compute_fuel_requirement(heavy(), &RocketEquation).unwrap();
+        assert_eq!(10 * (u32::MAX as u64 / 3 - 2), *fuel.fuel_for_modules());
+
+        // u128 totals can still overflow, with the fuel for fuel tipping the third module over
+        let heaviest = std::iter::repeat_n(Module { mass: u128::MAX }, 10);
+        assert_eq!(
+            Err("Fuel total overflowed at module 2".to_string()),
+            compute_fuel_requirement(heaviest, &RocketEquation)
+        );
+    }
+
+    #[test]
+    fn test_streaming_fuel_requirement() {
+        // a million modules, generated lazily
+        let modules = (0..1_000_000_u32).map(|i| Module { mass: 1969 + i % 7 });
+        let fuel = compute_fuel_requirement(modules, &RocketEquation).unwrap();
+        let per_cycle: u64 = (0..7_u32)
+            .map(|i| {
+                *compute_fuel_requirement([Module { mass: 1969 + i }], &RocketEquation)
+                    .unwrap()
+                    .fuel_for_modules_and_fuel()
+            })
+            .sum();
+        let remainder: u64 = (0..1_000_000_u32 % 7)
+            .map(|i| {
+                *compute_fuel_requirement([Module { mass: 1969 + i }], &RocketEquation)
+                    .unwrap()
+                    .fuel_for_modules_and_fuel()
+            })
+            .sum();
+        assert_eq!(
+            1_000_000 / 7 * per_cycle + remainder,
+            *fuel.fuel_for_modules_and_fuel()
+        );
+    }
+
+    /// Checks that every step of the cascade requires less fuel than the last, which guarantees
+    /// that the cascade terminates, before computing it.
+    fn check_cascade_terminates<F: FuelModel>(
+        model: &F,
+        mass: u32,
+    ) -> std::result::Result<(), TestCaseError> {
+        let mut m = mass;
+        while m > 0 {
+            let fuel = model.fuel_for_mass(m);
+            prop_assert!(fuel < m, "{} requires {} fuel", m, fuel);
+            m = fuel;
+        }
+        prop_assert_eq!(0, model.fuel_for_mass(0));
+
+        let cascade = fuel_cascade(mass, model);
+        prop_assert!(cascade.windows(2).all(|w| w[1] < w[0]));
+        Ok(())
+    }
+
+    proptest! {
+        #[test]
+        fn rocket_equation_cascade_terminates(mass in any::<u32>()) {
+            check_cascade_terminates(&RocketEquation, mass)?;
+        }
+
+        #[test]
+        fn divisor_offset_cascade_terminates(
+            divisor in 0..10_u32,
+            offset in 0..10_u32,
+            // a divisor of one only subtracts the offset, so large masses take billions of steps
+            mass in 0..100_000_u32,
+        ) {
+            if let Ok(model) = DivisorOffsetModel::new(divisor, offset) {
+                check_cascade_terminates(&model, mass)?;
+            }
+        }
+
+        #[test]
+        fn table_cascade_terminates(
+            steps in prop::collection::vec((any::<u32>(), any::<u32>()), 0..8),
+            mass in any::<u32>(),
+        ) {
+            if let Ok(model) = TableModel::new(steps) {
+                check_cascade_terminates(&model, mass)?;
+            }
+        }
+
+        #[test]
+        fn tsiolkovsky_cascade_terminates(
+            delta_v in 0.0..10.0_f64,
+            exhaust_velocity in 0.1..10.0_f64,
+            // fuel ratios close to one remove as little as one unit of mass per step
+            mass in 0..100_000_u32,
+        ) {
+            if let Ok(model) = TsiolkovskyModel::new(delta_v, exhaust_velocity) {
+                check_cascade_terminates(&model, mass)?;
+            }
+        }
+    }
+
+    #[test]
+    fn answer_breakdown() {
+        let breakdown = compute_fuel_breakdown(&get_modules(), &RocketEquation);
+        assert_eq!(
+            Ok(breakdown.total()),
+            compute_fuel_requirement(get_modules(), &RocketEquation)
+        );
     }
 
     #[test]
     fn answer() {
-        let ans = compute_fuel_requirement(&get_modules());
+        let ans = compute_fuel_requirement(get_modules(), &RocketEquation).unwrap();
         assert_eq!(3254441, ans.fuel_for_modules);
         assert_eq!(4878818, ans.fuel_for_modules_and_fuel);
     }
@@ -1,4 +1,4 @@
-use crate::day2::{IntCodeProgram, IntCodeProgramExecutor, MemContent, ProgramState};
+use crate::day2::{IntCodeProgram, IntCodeProgramExecutor, MemContent, MemoryAccess, ProgramState};
 use std::collections::BTreeMap;
 use std::fmt;
 
@@ -153,10 +153,24 @@ impl Robot {
 }
 
 fn paint(hull: &mut Hull) {
-    let mut robot = Robot::new();
+    let mut prog = get_program();
+    let mut exec = IntCodeProgramExecutor::from(&mut prog);
+    run_robot(hull, &mut exec);
+}
 
+/// Paints a black hull, returning the memory accesses made by the robot's program
+pub fn trace_robot_program() -> Vec<MemoryAccess> {
+    let mut hull = Hull::new();
     let mut prog = get_program();
     let mut exec = IntCodeProgramExecutor::from(&mut prog);
+    exec.enable_trace();
+    run_robot(&mut hull, &mut exec);
+    exec.trace().unwrap().clone()
+}
+
+fn run_robot(hull: &mut Hull, exec: &mut IntCodeProgramExecutor<&mut Vec<MemContent>>) {
+    let mut robot = Robot::new();
+
     let mut state = exec.execute();
     while let Ok(ref inner) = state {
         match inner {
@@ -170,7 +184,7 @@ fn paint(hull: &mut Hull) {
                 robot.turn(direction.into());
                 robot.forward();
             }
-            ProgramState::Terminated(out) => break,
+            ProgramState::Terminated(_) => break,
         }
     }
 
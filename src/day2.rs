@@ -1,13 +1,12 @@
 /// Implements an Intcode computer
 use std::convert::TryFrom;
-use std::convert::TryInto;
 
 pub type Result<T> = std::result::Result<T, String>;
 pub type MemContent = i64;
 pub type Addr = usize;
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
-enum ParameterMode {
+pub enum ParameterMode {
     /// Parameters are interpreted as a position.  If the parameter is 50, its value is the value
     /// stored at address 50 in memory.
     PositionMode,
@@ -20,78 +19,80 @@ enum ParameterMode {
     RelativeMode,
 }
 
+/// Reads the given address, treating memory beyond the end of the program as zero
+fn read_or_default(prog: &[MemContent], abs_addr: Addr) -> MemContent {
+    prog.get(abs_addr).cloned().unwrap_or_default()
+}
+
+/// Interprets a memory value as an address.  Negative addresses are invalid.
+fn to_addr(value: MemContent) -> Result<Addr> {
+    usize::try_from(value).map_err(|_| format!("Negative address: {}", value))
+}
+
+/// Offsets the base address by the (possibly negative) offset.
+fn offset_addr(base: Addr, offset: MemContent) -> Result<Addr> {
+    let addr = (base as MemContent)
+        .checked_add(offset)
+        .ok_or_else(|| format!("Arithmetic overflow offsetting {} by {}", base, offset))?;
+    to_addr(addr)
+}
+
 impl ParameterMode {
     // TODO: move into executor
-    fn parse(&self, prog: &Vec<MemContent>, loc: Addr, relative_base: Addr) -> MemContent {
-        fn read_or_default(prog: &Vec<MemContent>, abs_addr: Addr) -> MemContent {
-            if abs_addr > prog.len() {
-                0
-            } else {
-                prog[abs_addr]
-            }
-        }
-
+    fn parse(&self, prog: &[MemContent], loc: Addr, relative_base: Addr) -> Result<MemContent> {
         match self {
             ParameterMode::PositionMode => {
-                let read_pos = usize::try_from(read_or_default(prog, loc)).unwrap();
-                read_or_default(prog, read_pos)
+                let read_pos = to_addr(read_or_default(prog, loc))?;
+                Ok(read_or_default(prog, read_pos))
             }
-            ParameterMode::ImmediateMode => read_or_default(prog, loc),
+            ParameterMode::ImmediateMode => Ok(read_or_default(prog, loc)),
             ParameterMode::RelativeMode => {
-                let abs_loc;
-                let offset = read_or_default(prog, loc);
-                if offset < 0 {
-                    abs_loc = relative_base
-                        .checked_sub(usize::try_from(-offset).unwrap())
-                        .unwrap();
-                } else {
-                    abs_loc = relative_base + usize::try_from(offset).unwrap();
-                }
-
-                read_or_default(prog, abs_loc)
+                let abs_loc = offset_addr(relative_base, read_or_default(prog, loc))?;
+                Ok(read_or_default(prog, abs_loc))
             }
         }
     }
 }
 
+fn parse_parameter_mode(
+    prog: &[MemContent],
+    instr_ptr: Addr,
+    parameter_offset: usize,
+) -> Result<ParameterMode> {
+    ParameterMode::try_from(
+        read_or_default(prog, instr_ptr) / 10_i64.pow(parameter_offset as u32 + 1) % 10,
+    )
+}
+
 fn parse_parameter_value(
-    prog: &Vec<MemContent>,
+    prog: &[MemContent],
     instr_ptr: Addr,
     parameter_offset: usize,
     relative_base: usize,
-) -> MemContent {
-    let param_mode =
-        ParameterMode::try_from(prog[instr_ptr] / 10_i64.pow(parameter_offset as u32 + 1) % 10)
-            .unwrap();
+) -> Result<MemContent> {
+    let param_mode = parse_parameter_mode(prog, instr_ptr, parameter_offset)?;
     param_mode.parse(prog, instr_ptr + parameter_offset, relative_base)
 }
 
 fn parse_write_index(
-    prog: &Vec<MemContent>,
+    prog: &[MemContent],
     instr_ptr: Addr,
     parameter_offset: usize,
     relative_base: usize,
-) -> Addr {
-    let param_mode =
-        ParameterMode::try_from(prog[instr_ptr] / 10_i64.pow(parameter_offset as u32 + 1) % 10)
-            .unwrap();
-    assert_ne!(param_mode, ParameterMode::ImmediateMode);
-    // TODO: I think we should be able to use this...  but it doesn't work
-    // param_mode
-    //     .parse(prog, instr_ptr + parameter_offset, relative_base)
-    //     .try_into()
-    //     .unwrap()
-    let offset = if param_mode == ParameterMode::RelativeMode {
-        relative_base
-    } else {
-        0
-    };
-
-    usize::try_from(prog[instr_ptr + parameter_offset] + offset as i64).unwrap()
+) -> Result<Addr> {
+    let param_mode = parse_parameter_mode(prog, instr_ptr, parameter_offset)?;
+    let param = read_or_default(prog, instr_ptr + parameter_offset);
+    match param_mode {
+        ParameterMode::PositionMode => to_addr(param),
+        ParameterMode::RelativeMode => offset_addr(relative_base, param),
+        ParameterMode::ImmediateMode => {
+            Err(format!("Immediate mode write at instruction {}", instr_ptr))
+        }
+    }
 }
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
-enum OpCode {
+pub enum OpCode {
     Add,
     Multiply,
     Input,
@@ -104,6 +105,39 @@ enum OpCode {
     Halt,
 }
 
+impl OpCode {
+    /// The number of parameters following the opcode
+    pub fn num_params(&self) -> usize {
+        match self {
+            OpCode::Add | OpCode::Multiply | OpCode::LessThan | OpCode::Equals => 3,
+            OpCode::JumpIfTrue | OpCode::JumpIfFalse => 2,
+            OpCode::Input | OpCode::Output | OpCode::RelativeBaseOffsetAdj => 1,
+            OpCode::Halt => 0,
+        }
+    }
+
+    /// The offset of the parameter that the instruction writes to, if any
+    pub fn write_param(&self) -> Option<usize> {
+        match self {
+            OpCode::Add | OpCode::Multiply | OpCode::LessThan | OpCode::Equals => Some(3),
+            OpCode::Input => Some(1),
+            _ => None,
+        }
+    }
+}
+
+/// Decodes the instruction at the given address into its opcode and the modes of its parameters
+pub fn decode_instruction(
+    prog: &[MemContent],
+    instr_ptr: Addr,
+) -> Result<(OpCode, Vec<ParameterMode>)> {
+    let opcode = OpCode::try_from(read_or_default(prog, instr_ptr) % 100)?;
+    let modes = (1..=opcode.num_params())
+        .map(|offset| parse_parameter_mode(prog, instr_ptr, offset))
+        .collect::<Result<_>>()?;
+    Ok((opcode, modes))
+}
+
 impl TryFrom<MemContent> for OpCode {
     type Error = String;
     fn try_from(u: MemContent) -> Result<OpCode> {
@@ -135,6 +169,22 @@ impl TryFrom<MemContent> for ParameterMode {
     }
 }
 
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum AccessKind {
+    Read,
+    Write,
+    Execute,
+}
+
+/// A single memory access made while executing an instruction
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct MemoryAccess {
+    /// The number of instructions executed before this one
+    pub step: usize,
+    pub addr: Addr,
+    pub kind: AccessKind,
+}
+
 pub struct IntCodeProgramExecutor<T> {
     program: T,
     noun: MemContent,
@@ -143,12 +193,21 @@ pub struct IntCodeProgramExecutor<T> {
     relative_base: Addr,
     input: Vec<MemContent>,
     pub output: Vec<MemContent>,
+
+    /// The number of instructions executed so far
+    steps: usize,
+
+    /// The number of values output so far
+    outputs_produced: usize,
+
+    /// Memory accesses made by executed instructions, if tracing is enabled
+    trace: Option<Vec<MemoryAccess>>,
 }
 
 impl From<Vec<MemContent>> for IntCodeProgramExecutor<Vec<MemContent>> {
     fn from(program: Vec<MemContent>) -> Self {
-        let noun = program[1];
-        let verb = program[2];
+        let noun = read_or_default(&program, 1);
+        let verb = read_or_default(&program, 2);
         IntCodeProgramExecutor {
             program,
             noun,
@@ -157,14 +216,17 @@ impl From<Vec<MemContent>> for IntCodeProgramExecutor<Vec<MemContent>> {
             relative_base: 0,
             input: Vec::new(),
             output: Vec::new(),
+            steps: 0,
+            outputs_produced: 0,
+            trace: None,
         }
     }
 }
 
 impl<'a> From<&'a mut Vec<MemContent>> for IntCodeProgramExecutor<&'a mut Vec<MemContent>> {
     fn from(program: &'a mut Vec<MemContent>) -> Self {
-        let noun = program[1];
-        let verb = program[2];
+        let noun = read_or_default(program, 1);
+        let verb = read_or_default(program, 2);
         IntCodeProgramExecutor {
             program,
             noun,
@@ -173,14 +235,32 @@ impl<'a> From<&'a mut Vec<MemContent>> for IntCodeProgramExecutor<&'a mut Vec<Me
             instr_ptr: 0,
             input: Vec::new(),
             output: Vec::new(),
+            steps: 0,
+            outputs_produced: 0,
+            trace: None,
         }
     }
 }
 
+/// The state of the executor when a program halts
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct RunSummary {
+    /// The address of the halt instruction
+    pub instr_ptr: Addr,
+
+    /// The number of instructions executed, including the halt instruction
+    pub instructions_executed: usize,
+
+    /// The number of values written to the output queue over the whole run
+    pub outputs_produced: usize,
+
+    pub relative_base: Addr,
+}
+
 #[derive(Debug)]
 pub enum ProgramState {
     AwaitingInput,
-    Terminated(MemContent),
+    Terminated(RunSummary),
 }
 
 // TODO: remove this, let IntCodeProgramExecutor own program, and add accessor
@@ -205,29 +285,46 @@ impl IntCodeProgramExecutor<&mut Vec<MemContent>> {
         self.program[loc] = content;
     }
 
+    /// Starts recording the memory accesses made by each executed instruction
+    pub fn enable_trace(&mut self) {
+        if self.trace.is_none() {
+            self.trace = Some(Vec::new());
+        }
+    }
+
+    /// Returns the memory accesses recorded since tracing was enabled
+    pub fn trace(&self) -> Option<&Vec<MemoryAccess>> {
+        self.trace.as_ref()
+    }
+
     pub fn execute(&mut self) -> Result<ProgramState> {
         loop {
             // The opcode is a two-digit number based only on the ones and tens digit of the value
-            let opcode = OpCode::try_from(self.program[self.instr_ptr] % 100)?;
+            let opcode = OpCode::try_from(read_or_default(self.program, self.instr_ptr) % 100)?;
+            let accesses = if self.trace.is_some() {
+                self.instruction_accesses(opcode)?
+            } else {
+                Vec::new()
+            };
             match opcode {
                 OpCode::Add => {
-                    let a1 = self.get_param(1);
-                    let a2 = self.get_param(2);
-                    let dest = self.get_write_index(3);
+                    let a1 = self.get_param(1)?;
+                    let a2 = self.get_param(2)?;
+                    let dest = self.get_write_index(3)?;
 
-                    self.write(dest, a1 + a2);
+                    self.write(dest, checked_arith(a1.checked_add(a2), self.instr_ptr)?);
                     self.instr_ptr += 4;
                 }
                 OpCode::Multiply => {
-                    let a1 = self.get_param(1);
-                    let a2 = self.get_param(2);
-                    let dest = self.get_write_index(3);
+                    let a1 = self.get_param(1)?;
+                    let a2 = self.get_param(2)?;
+                    let dest = self.get_write_index(3)?;
 
-                    self.write(dest, a1 * a2);
+                    self.write(dest, checked_arith(a1.checked_mul(a2), self.instr_ptr)?);
                     self.instr_ptr += 4;
                 }
                 OpCode::Input => {
-                    let store_addr = self.get_write_index(1);
+                    let store_addr = self.get_write_index(1)?;
                     if self.input.is_empty() {
                         return Ok(ProgramState::AwaitingInput);
                     }
@@ -236,68 +333,105 @@ impl IntCodeProgramExecutor<&mut Vec<MemContent>> {
                     self.instr_ptr += 2;
                 }
                 OpCode::Output => {
-                    let output_value = self.get_param(1);
+                    let output_value = self.get_param(1)?;
                     self.output.push(output_value);
+                    self.outputs_produced += 1;
                     self.instr_ptr += 2;
                 }
                 OpCode::JumpIfTrue => {
-                    let a1 = self.get_param(1);
-                    let a2 = self.get_param(2);
+                    let a1 = self.get_param(1)?;
+                    let a2 = self.get_param(2)?;
 
                     if a1 != 0 {
-                        self.instr_ptr = a2.try_into().unwrap();
+                        self.instr_ptr = to_addr(a2)?;
                     } else {
                         self.instr_ptr += 3;
                     }
                 }
                 OpCode::JumpIfFalse => {
-                    let a1 = self.get_param(1);
-                    let a2 = self.get_param(2);
+                    let a1 = self.get_param(1)?;
+                    let a2 = self.get_param(2)?;
 
                     if a1 == 0 {
                         // instruction pointer modified.  do not advance instruction pointer
-                        self.instr_ptr = a2.try_into().unwrap();
+                        self.instr_ptr = to_addr(a2)?;
                     } else {
                         self.instr_ptr += 3;
                     }
                 }
                 OpCode::LessThan => {
-                    let a1 = self.get_param(1);
-                    let a2 = self.get_param(2);
-                    let a3 = self.get_write_index(3);
+                    let a1 = self.get_param(1)?;
+                    let a2 = self.get_param(2)?;
+                    let a3 = self.get_write_index(3)?;
 
                     self.write(a3, if a1 < a2 { 1 } else { 0 });
                     self.instr_ptr += 4;
                 }
                 OpCode::Equals => {
-                    let a1 = self.get_param(1);
-                    let a2 = self.get_param(2);
-                    let a3 = self.get_write_index(3);
+                    let a1 = self.get_param(1)?;
+                    let a2 = self.get_param(2)?;
+                    let a3 = self.get_write_index(3)?;
 
                     self.write(a3, if a1 == a2 { 1 } else { 0 });
                     self.instr_ptr += 4;
                 }
                 OpCode::RelativeBaseOffsetAdj => {
-                    let orig = self.relative_base;
-                    let adjustment = self.get_param(1);
-                    if adjustment < 0 {
-                        self.relative_base = self
-                            .relative_base
-                            .checked_sub(usize::try_from(-adjustment).unwrap())
-                            .unwrap();
-                    } else {
-                        self.relative_base =
-                            self.relative_base + usize::try_from(adjustment).unwrap();
-                    }
+                    let adjustment = self.get_param(1)?;
+                    self.relative_base = offset_addr(self.relative_base, adjustment)?;
                     self.instr_ptr += 2;
                 }
-                OpCode::Halt => break,
+                OpCode::Halt => {
+                    self.complete_instruction(accesses);
+                    break;
+                }
             }
+            self.complete_instruction(accesses);
+        }
+        Ok(ProgramState::Terminated(self.summary()))
+    }
+
+    /// Summarizes the execution so far
+    pub fn summary(&self) -> RunSummary {
+        RunSummary {
+            instr_ptr: self.instr_ptr,
+            instructions_executed: self.steps,
+            outputs_produced: self.outputs_produced,
+            relative_base: self.relative_base,
         }
-        Ok(ProgramState::Terminated(self.program[0]))
     }
 
-    fn get_param(&self, param_offset: usize) -> MemContent {
+    fn complete_instruction(&mut self, accesses: Vec<MemoryAccess>) {
+        if let Some(trace) = &mut self.trace {
+            trace.extend(accesses);
+        }
+        self.steps += 1;
+    }
+
+    /// Computes the memory accesses that the instruction at the instruction pointer will make
+    fn instruction_accesses(&self, opcode: OpCode) -> Result<Vec<MemoryAccess>> {
+        let step = self.steps;
+        let access = |addr, kind| MemoryAccess { step, addr, kind };
+        let mut accesses: Vec<_> = (0..=opcode.num_params())
+            .map(|offset| access(self.instr_ptr + offset, AccessKind::Execute))
+            .collect();
+        for offset in 1..=opcode.num_params() {
+            if opcode.write_param() == Some(offset) {
+                accesses.push(access(self.get_write_index(offset)?, AccessKind::Write));
+                continue;
+            }
+
+            let param = read_or_default(self.program, self.instr_ptr + offset);
+            let addr = match parse_parameter_mode(self.program, self.instr_ptr, offset)? {
+                ParameterMode::PositionMode => to_addr(param)?,
+                ParameterMode::RelativeMode => offset_addr(self.relative_base, param)?,
+                ParameterMode::ImmediateMode => continue,
+            };
+            accesses.push(access(addr, AccessKind::Read));
+        }
+        Ok(accesses)
+    }
+
+    fn get_param(&self, param_offset: usize) -> Result<MemContent> {
         parse_parameter_value(
             self.program,
             self.instr_ptr,
@@ -306,7 +440,7 @@ impl IntCodeProgramExecutor<&mut Vec<MemContent>> {
         )
     }
 
-    fn get_write_index(&self, param_offset: usize) -> Addr {
+    fn get_write_index(&self, param_offset: usize) -> Result<Addr> {
         parse_write_index(
             self.program,
             self.instr_ptr,
@@ -316,18 +450,28 @@ impl IntCodeProgramExecutor<&mut Vec<MemContent>> {
     }
 }
 
+/// Converts the result of a checked arithmetic operation into an error on overflow
+fn checked_arith(result: Option<MemContent>, instr_ptr: Addr) -> Result<MemContent> {
+    result.ok_or_else(|| format!("Arithmetic overflow at instruction {}", instr_ptr))
+}
+
 pub trait IntCodeProgram {
-    fn execute(&mut self) -> Result<MemContent>;
+    fn execute(&mut self) -> Result<RunSummary>;
+
+    /// Executes a Day 2 style program with the given noun and verb, returning its result
     fn execute_with_args(&mut self, arg1: MemContent, arg2: MemContent) -> Result<MemContent>;
-    fn output(&self) -> MemContent;
+
+    /// The value at address 0, which is where Day 2 style programs leave their result
+    fn day2_output(&self) -> MemContent;
+
     fn search_for_output(&self, target_output: MemContent) -> Result<(MemContent, MemContent)>;
 }
 
 impl IntCodeProgram for Vec<MemContent> {
-    fn execute(&mut self) -> Result<MemContent> {
+    fn execute(&mut self) -> Result<RunSummary> {
         let state = IntCodeProgramExecutor::from(self).execute();
-        if let Ok(ProgramState::Terminated(result)) = state {
-            Ok(result)
+        if let Ok(ProgramState::Terminated(summary)) = state {
+            Ok(summary)
         } else {
             panic!("Execution returned with unexpected state: {:?}", state);
         }
@@ -336,10 +480,11 @@ impl IntCodeProgram for Vec<MemContent> {
     fn execute_with_args(&mut self, noun: MemContent, verb: MemContent) -> Result<MemContent> {
         self[1] = noun;
         self[2] = verb;
-        self.execute()
+        self.execute()?;
+        Ok(self.day2_output())
     }
 
-    fn output(&self) -> MemContent {
+    fn day2_output(&self) -> MemContent {
         self[0]
     }
 
@@ -408,8 +553,44 @@ mod tests {
     #[test]
     fn answer_part_1() {
         let mut prog = get_gravity_assist_program();
-        prog.execute_with_args(12, 2).unwrap();
-        assert_eq!(3267740, prog[0]);
+        assert_eq!(3267740, prog.execute_with_args(12, 2).unwrap());
+        assert_eq!(3267740, prog.day2_output());
+    }
+
+    #[test]
+    fn test_run_summary() {
+        let mut prog = vec![109, 19, 104, 7, 104, 8, 99];
+        let mut exec = IntCodeProgramExecutor::from(&mut prog);
+        match exec.execute().unwrap() {
+            ProgramState::Terminated(summary) => assert_eq!(
+                RunSummary {
+                    instr_ptr: 6,
+                    instructions_executed: 4,
+                    outputs_produced: 2,
+                    relative_base: 19,
+                },
+                summary
+            ),
+            state => panic!("Unexpected state: {:?}", state),
+        }
+    }
+
+    #[test]
+    fn test_run_summary_across_resume() {
+        // outputs produced counts values already taken from the output queue
+        let mut prog = vec![3, 9, 4, 9, 3, 9, 4, 9, 99, 0];
+        let mut exec = IntCodeProgramExecutor::from(&mut prog);
+        exec.execute().unwrap();
+        exec.resume(1).unwrap();
+        exec.output.remove(0);
+        let summary = match exec.resume(2).unwrap() {
+            ProgramState::Terminated(summary) => summary,
+            state => panic!("Unexpected state: {:?}", state),
+        };
+        assert_eq!(8, summary.instr_ptr);
+        assert_eq!(5, summary.instructions_executed);
+        assert_eq!(2, summary.outputs_produced);
+        assert_eq!(vec![2], exec.output);
     }
 
     #[test]
@@ -462,9 +643,9 @@ mod tests {
     fn test_parse_parameter_value() {
         let prog = vec![1002, 4, 3, 4, 33];
         let instr_ptr = 0;
-        let p1 = parse_parameter_value(&prog, instr_ptr, 1, 0);
-        let p2 = parse_parameter_value(&prog, instr_ptr, 2, 0);
-        let p3 = parse_write_index(&prog, instr_ptr, 3, 0);
+        let p1 = parse_parameter_value(&prog, instr_ptr, 1, 0).unwrap();
+        let p2 = parse_parameter_value(&prog, instr_ptr, 2, 0).unwrap();
+        let p3 = parse_write_index(&prog, instr_ptr, 3, 0).unwrap();
         assert_eq!(p1, 33);
         assert_eq!(p2, 3);
         assert_eq!(p3, 4);
@@ -1,5 +1,6 @@
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, HashMap};
 use std::convert::TryFrom;
+use std::fmt;
 
 pub fn get_paths() -> (Vec<Movement>, Vec<Movement>) {
     let p1 = "R999, D666, L86, U464, R755, U652, R883, D287, L244, U308, L965, U629, R813, U985, \
@@ -47,7 +48,7 @@ pub fn get_paths() -> (Vec<Movement>, Vec<Movement>) {
               L888, U232, L740, D348, R445, U269, L170, U979, L159, U433, L31, D818, L914, U600, \
               L33, U159, R974, D983, L922, U807, R682, U525, L234, U624, L973, U123, L875, D64, \
               L579, U885, L911, D578, R17, D293, L211";
-    (parse_path(&p1), parse_path(&p2))
+    (parse_path(p1).unwrap(), parse_path(p2).unwrap())
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -60,6 +61,11 @@ impl Movement {
     fn new(dir: Direction, amount: usize) -> Movement {
         Movement { dir, amount }
     }
+
+    /// The number of steps in the movement
+    pub fn amount(&self) -> usize {
+        self.amount
+    }
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -73,6 +79,62 @@ enum Direction {
 pub trait Path {
     fn iterator(&self) -> PathIterator;
     fn path_length(&self) -> usize;
+    fn wire(&self) -> Wire;
+
+    /// Every position the path visits more than once, in position order.  The origin counts as
+    /// visited at step 0.
+    fn self_intersections(&self) -> Vec<Revisit>;
+
+    /// Every loop in the path, between consecutive visits to the same position, in the order
+    /// they're closed
+    fn loops(&self) -> Vec<Loop>;
+
+    /// An equivalent path, from the origin to the same end position, with every loop removed.
+    /// The result never visits a position twice.
+    fn simplify(&self) -> Vec<Movement>;
+}
+
+/// A position that a path visits more than once
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Revisit {
+    pub pos: PathPosition,
+
+    /// The number of steps taken at each visit, in increasing order
+    pub steps: Vec<usize>,
+}
+
+/// The part of a path between two consecutive visits to the same position
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Loop {
+    pub pos: PathPosition,
+    pub start_step: usize,
+    pub end_step: usize,
+}
+
+impl Loop {
+    pub fn len(&self) -> usize {
+        self.end_step - self.start_step
+    }
+}
+
+/// Converts a walk between adjacent positions back into movements, merging consecutive steps
+/// in the same direction
+fn walk_to_movements(walk: &[PathPosition]) -> Vec<Movement> {
+    let mut movements: Vec<Movement> = Vec::new();
+    for pair in walk.windows(2) {
+        let dir = match (pair[1].0 - pair[0].0, pair[1].1 - pair[0].1) {
+            (1, 0) => Direction::Up,
+            (-1, 0) => Direction::Down,
+            (0, -1) => Direction::Left,
+            (0, 1) => Direction::Right,
+            delta => panic!("Positions {:?} aren't adjacent: {:?}", pair, delta),
+        };
+        match movements.last_mut() {
+            Some(last) if last.dir == dir => last.amount += 1,
+            _ => movements.push(Movement::new(dir, 1)),
+        }
+    }
+    movements
 }
 
 impl Path for Vec<Movement> {
@@ -88,11 +150,83 @@ impl Path for Vec<Movement> {
     fn path_length(&self) -> usize {
         self.iter().map(|c| c.amount).sum()
     }
+
+    fn wire(&self) -> Wire {
+        Wire::new(self)
+    }
+
+    fn self_intersections(&self) -> Vec<Revisit> {
+        let mut visits: HashMap<PathPosition, Vec<usize>> = HashMap::new();
+        visits.insert(PathPosition(0, 0), vec![0]);
+        for (idx, pos) in self.iterator().enumerate() {
+            visits.entry(pos).or_default().push(idx + 1);
+        }
+        let mut revisits: Vec<Revisit> = visits
+            .into_iter()
+            .filter(|(_, steps)| steps.len() > 1)
+            .map(|(pos, steps)| Revisit { pos, steps })
+            .collect();
+        revisits.sort_by_key(|r| r.pos);
+        revisits
+    }
+
+    fn loops(&self) -> Vec<Loop> {
+        let mut loops: Vec<Loop> = self
+            .self_intersections()
+            .iter()
+            .flat_map(|r| {
+                r.steps.windows(2).map(move |w| Loop {
+                    pos: r.pos,
+                    start_step: w[0],
+                    end_step: w[1],
+                })
+            })
+            .collect();
+        loops.sort_by_key(|l| l.end_step);
+        loops
+    }
+
+    fn simplify(&self) -> Vec<Movement> {
+        // walk the path, cutting the walk back whenever it returns to a position it's still on
+        let mut walk = vec![PathPosition(0, 0)];
+        let mut on_walk: HashMap<PathPosition, usize> = HashMap::new();
+        on_walk.insert(PathPosition(0, 0), 0);
+        for pos in self.iterator() {
+            match on_walk.get(&pos) {
+                Some(&idx) => {
+                    for removed in walk.drain(idx + 1..) {
+                        on_walk.remove(&removed);
+                    }
+                }
+                None => {
+                    on_walk.insert(pos, walk.len());
+                    walk.push(pos);
+                }
+            }
+        }
+        walk_to_movements(&walk)
+    }
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Ord, PartialOrd, Hash)]
 pub struct PathPosition(i32, i32);
 
+impl PathPosition {
+    pub fn new(row: i32, col: i32) -> PathPosition {
+        PathPosition(row, col)
+    }
+
+    /// The vertical coordinate, increasing upwards
+    pub fn row(&self) -> i32 {
+        self.0
+    }
+
+    /// The horizontal coordinate, increasing to the right
+    pub fn col(&self) -> i32 {
+        self.1
+    }
+}
+
 pub struct PathIterator<'a> {
     /// The path that is being iterated over
     path: &'a Vec<Movement>,
@@ -137,29 +271,355 @@ impl<'a> Iterator for PathIterator<'a> {
     }
 }
 
+/// A straight run of a wire, covering the cells one to `len` steps from `start`
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Segment {
+    start: PathPosition,
+    dir: Direction,
+    len: usize,
+
+    /// The number of steps along the wire before the start of the segment
+    start_step: usize,
+}
+
+impl Segment {
+    pub fn start(&self) -> PathPosition {
+        self.start
+    }
+
+    pub fn end(&self) -> PathPosition {
+        let PathPosition(row, col) = self.start;
+        let len = self.len as i32;
+        match self.dir {
+            Direction::Up => PathPosition(row + len, col),
+            Direction::Down => PathPosition(row - len, col),
+            Direction::Left => PathPosition(row, col - len),
+            Direction::Right => PathPosition(row, col + len),
+        }
+    }
+
+    fn is_horizontal(&self) -> bool {
+        self.dir == Direction::Left || self.dir == Direction::Right
+    }
+
+    /// The fixed coordinate of the line the segment runs along, and the range it covers on
+    /// that line, including its start
+    fn line_and_range(&self) -> (i32, i32, i32) {
+        let (start, end) = (self.start, self.end());
+        if self.is_horizontal() {
+            (start.0, start.1.min(end.1), start.1.max(end.1))
+        } else {
+            (start.1, start.0.min(end.0), start.0.max(end.0))
+        }
+    }
+
+    /// The number of steps along the wire to reach a position on the segment
+    fn step_at(&self, pos: PathPosition) -> usize {
+        self.start_step + ((pos.0 - self.start.0).abs() + (pos.1 - self.start.1).abs()) as usize
+    }
+}
+
+/// A wire as a list of straight segments, rather than the individual cells it passes through
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Wire {
+    segments: Vec<Segment>,
+}
+
+impl Wire {
+    pub fn new(path: &[Movement]) -> Wire {
+        let mut segments = Vec::with_capacity(path.len());
+        let mut start = PathPosition(0, 0);
+        let mut start_step = 0;
+        for mv in path.iter().filter(|mv| mv.amount > 0) {
+            let segment = Segment {
+                start,
+                dir: mv.dir,
+                len: mv.amount,
+                start_step,
+            };
+            start = segment.end();
+            start_step += mv.amount;
+            segments.push(segment);
+        }
+        Wire { segments }
+    }
+
+    pub fn segments(&self) -> &[Segment] {
+        &self.segments
+    }
+}
+
+/// A segment's range on its line, from `line_and_range`, and the index of its wire
+type LineSpan<'a> = (i32, i32, usize, &'a Segment);
+
+/// For every cell visited by at least two of the wires, the step at which each of those wires
+/// first reaches it, keyed by wire index.  The origin only counts if a wire returns to it.
+///
+/// Perpendicular crossings are found with a sweep over columns, keeping the horizontal segments
+/// that span the current column in a tree ordered by row, so that each vertical segment finds
+/// the horizontal segments it crosses with a range query.  Collinear overlaps are found by
+/// sorting the segments on each line.  Together this takes O((n + k) log n) time for n segments
+/// and k crossings, independently of the length of the wires.
+fn wire_hits(wires: &[&Wire]) -> BTreeMap<PathPosition, BTreeMap<usize, usize>> {
+    let mut hits: BTreeMap<PathPosition, BTreeMap<usize, usize>> = BTreeMap::new();
+    let mut record = |pos: PathPosition, wire: usize, segment: &Segment| {
+        let step = segment.step_at(pos);
+        // the start of the wire hasn't been visited, only started from
+        if step > 0 {
+            let first = hits.entry(pos).or_default().entry(wire).or_insert(step);
+            *first = (*first).min(step);
+        }
+    };
+
+    // sweep events at each column: horizontal segments start, then vertical segments are
+    // checked against the active horizontal segments, then horizontal segments end
+    const START: u8 = 0;
+    const QUERY: u8 = 1;
+    const END: u8 = 2;
+    let mut events = Vec::new();
+    // segments along each row (horizontal) or column (vertical), for collinear overlaps
+    let mut lines: BTreeMap<(bool, i32), Vec<LineSpan>> = BTreeMap::new();
+    for (w, wire) in wires.iter().enumerate() {
+        for segment in &wire.segments {
+            let (line, lo, hi) = segment.line_and_range();
+            if segment.is_horizontal() {
+                events.push((lo, START, w, segment));
+                events.push((hi, END, w, segment));
+            } else {
+                events.push((line, QUERY, w, segment));
+            }
+            lines
+                .entry((segment.is_horizontal(), line))
+                .or_default()
+                .push((lo, hi, w, segment));
+        }
+    }
+    events.sort_by_key(|&(col, kind, w, segment)| (col, kind, w, segment.start_step));
+
+    let mut active: BTreeMap<(i32, usize, usize), &Segment> = BTreeMap::new();
+    for (col, kind, w, segment) in events {
+        let (line, lo, hi) = segment.line_and_range();
+        match kind {
+            START => {
+                active.insert((line, w, segment.start_step), segment);
+            }
+            END => {
+                active.remove(&(line, w, segment.start_step));
+            }
+            _ => {
+                let range = (lo, 0, 0)..=(hi, usize::MAX, usize::MAX);
+                for (&(row, other_w, _), &other) in active.range(range) {
+                    if other_w != w {
+                        let pos = PathPosition(row, col);
+                        record(pos, w, segment);
+                        record(pos, other_w, other);
+                    }
+                }
+            }
+        }
+    }
+
+    for ((horizontal, line), mut segments) in lines {
+        segments.sort_by_key(|&(lo, hi, w, _)| (lo, hi, w));
+        let mut open: Vec<LineSpan> = Vec::new();
+        for (lo, hi, w, segment) in segments {
+            open.retain(|&(_, open_hi, _, _)| open_hi >= lo);
+            for &(_, open_hi, open_w, other) in open.iter().filter(|o| o.2 != w) {
+                for i in lo..=hi.min(open_hi) {
+                    let pos = if horizontal {
+                        PathPosition(line, i)
+                    } else {
+                        PathPosition(i, line)
+                    };
+                    record(pos, w, segment);
+                    record(pos, open_w, other);
+                }
+            }
+            open.push((lo, hi, w, segment));
+        }
+    }
+
+    hits.retain(|_, wires| wires.len() >= 2);
+    hits
+}
+
+/// A cell visited by both of two wires
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Crossing {
+    pub pos: PathPosition,
+
+    /// The number of steps each wire takes to first reach the crossing
+    pub steps: (usize, usize),
+}
+
+/// Finds the cells visited by both wires, in position order
+pub fn wire_crossings(w1: &Wire, w2: &Wire) -> Vec<Crossing> {
+    wire_hits(&[w1, w2])
+        .into_iter()
+        .map(|(pos, steps)| Crossing {
+            pos,
+            steps: (steps[&0], steps[&1]),
+        })
+        .collect()
+}
+
 pub fn intersect<P: Path>(p1: &P, p2: &P) -> Vec<PathPosition> {
-    let p1: BTreeSet<_> = p1.iterator().collect();
-    let p2: BTreeSet<_> = p2.iterator().collect();
+    wire_crossings(&p1.wire(), &p2.wire())
+        .iter()
+        .map(|c| c.pos)
+        .collect()
+}
+
+/// A cell where two or more wires meet
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Junction {
+    pub pos: PathPosition,
+
+    /// The number of steps each wire that meets here takes to first reach the junction, keyed by
+    /// wire index
+    pub steps: BTreeMap<usize, usize>,
+}
+
+impl Junction {
+    pub fn manhattan_distance(&self) -> usize {
+        (self.pos.0.abs() + self.pos.1.abs()) as usize
+    }
+}
+
+/// How to measure the distance to a crossing
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Metric {
+    /// Manhattan distance from the origin
+    Manhattan,
+
+    /// The combined number of steps the wires take to reach the crossing
+    Steps,
+}
+
+/// Any number of wires, and every junction between them
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Circuit {
+    wires: Vec<Wire>,
+
+    /// In position order
+    junctions: Vec<Junction>,
+}
+
+impl Circuit {
+    pub fn new<P: Path>(paths: &[P]) -> Circuit {
+        let wires: Vec<Wire> = paths.iter().map(Path::wire).collect();
+        let junctions = wire_hits(&wires.iter().collect::<Vec<_>>())
+            .into_iter()
+            .map(|(pos, steps)| Junction { pos, steps })
+            .collect();
+        Circuit { wires, junctions }
+    }
+
+    pub fn wires(&self) -> &[Wire] {
+        &self.wires
+    }
+
+    pub fn junctions(&self) -> &[Junction] {
+        &self.junctions
+    }
+
+    /// The junctions where at least `k` wires meet
+    pub fn junctions_of_at_least(&self, k: usize) -> impl Iterator<Item = &Junction> {
+        self.junctions.iter().filter(move |j| j.steps.len() >= k)
+    }
+
+    /// The junctions where wires `i` and `j` both meet, possibly along with other wires
+    pub fn crossings_of(&self, i: usize, j: usize) -> impl Iterator<Item = &Junction> {
+        self.junctions.iter().filter(move |junction| {
+            i != j && junction.steps.contains_key(&i) && junction.steps.contains_key(&j)
+        })
+    }
+
+    /// The crossing of wires `i` and `j` closest by the given metric, and its distance.  With
+    /// `Metric::Steps`, only the steps of wires `i` and `j` are counted.  Ties are broken by
+    /// position.
+    pub fn closest_crossing(
+        &self,
+        i: usize,
+        j: usize,
+        metric: Metric,
+    ) -> Option<(&Junction, usize)> {
+        self.crossings_of(i, j)
+            .map(|junction| {
+                let distance = match metric {
+                    Metric::Manhattan => junction.manhattan_distance(),
+                    Metric::Steps => junction.steps[&i] + junction.steps[&j],
+                };
+                (junction, distance)
+            })
+            .min_by_key(|&(junction, distance)| (distance, junction.pos))
+    }
+}
+
+/// A token in a wire path that couldn't be parsed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParsePathError {
+    /// Index of the wire, when parsing several wires
+    pub wire: usize,
 
-    p1.intersection(&p2).cloned().collect()
+    /// Index of the token within the wire
+    pub index: usize,
+    pub token: String,
+    pub reason: String,
 }
 
-fn parse_path(s: &str) -> Vec<Movement> {
+impl fmt::Display for ParsePathError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "wire {}, token {} '{}': {}",
+            self.wire, self.index, self.token, self.reason
+        )
+    }
+}
+
+/// Parses a comma separated list of movements, such as `R8,U5` or `r 8, u 5`.  Directions may
+/// be upper or lower case, and whitespace is ignored.
+pub fn parse_path(s: &str) -> Result<Vec<Movement>, ParsePathError> {
     let mut movement = Vec::new();
-    for tok in s.split(',') {
-        let tok = tok.trim();
-        let dir = tok.chars().nth(0).unwrap();
-        let dist = *&tok[1..].parse::<usize>().unwrap();
-        let mv = match dir {
-            'L' => Movement::new(Direction::Left, dist),
-            'R' => Movement::new(Direction::Right, dist),
-            'U' => Movement::new(Direction::Up, dist),
-            'D' => Movement::new(Direction::Down, dist),
-            _ => panic!(format!("Unexpected direction: {}", dir)),
+    for (index, raw) in s.split(',').enumerate() {
+        let tok: String = raw.split_whitespace().collect();
+        let err = |reason: &str| ParsePathError {
+            wire: 0,
+            index,
+            token: raw.trim().to_string(),
+            reason: reason.to_string(),
+        };
+        let mut chars = tok.chars();
+        let dir = match chars.next().map(|c| c.to_ascii_uppercase()) {
+            None => return Err(err("empty token")),
+            Some('L') => Direction::Left,
+            Some('R') => Direction::Right,
+            Some('U') => Direction::Up,
+            Some('D') => Direction::Down,
+            Some(c) => return Err(err(&format!("unexpected direction '{}'", c))),
         };
-        movement.push(mv);
+        let dist = chars.as_str();
+        let dist = match dist.parse::<usize>() {
+            Ok(0) => return Err(err("distance must be positive")),
+            // positions are i32, so longer movements would overflow
+            Ok(d) if d > i32::MAX as usize => return Err(err("distance is too large")),
+            Ok(d) => d,
+            Err(_) if dist.is_empty() => return Err(err("missing distance")),
+            Err(_) => return Err(err(&format!("invalid distance '{}'", dist))),
+        };
+        movement.push(Movement::new(dir, dist));
     }
-    movement
+    Ok(movement)
+}
+
+/// Parses any number of wires, one per line.  Blank lines are skipped.
+pub fn parse_wires(s: &str) -> Result<Vec<Vec<Movement>>, ParsePathError> {
+    s.lines()
+        .filter(|line| !line.trim().is_empty())
+        .enumerate()
+        .map(|(wire, line)| parse_path(line).map_err(|e| ParsePathError { wire, ..e }))
+        .collect()
 }
 
 pub fn compute_min_manhattan_distance(pos: &Vec<PathPosition>) -> Option<i32> {
@@ -168,35 +628,347 @@ pub fn compute_min_manhattan_distance(pos: &Vec<PathPosition>) -> Option<i32> {
         .min()
 }
 
+/// Maps between the positions a path visits and the number of steps taken to reach them, so
+/// that step lookups don't need to walk the path
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WireIndex {
+    /// The position after each number of steps, starting from the origin at step 0
+    positions: Vec<PathPosition>,
+
+    /// The first step at which each position is reached.  The origin is only included if the
+    /// path returns to it, since it's started from rather than reached.
+    first_steps: HashMap<PathPosition, usize>,
+}
+
+impl WireIndex {
+    pub fn new<P: Path>(path: &P) -> WireIndex {
+        let mut positions = Vec::with_capacity(path.path_length() + 1);
+        positions.push(PathPosition(0, 0));
+        let mut first_steps = HashMap::with_capacity(path.path_length());
+        for (idx, pos) in path.iterator().enumerate() {
+            positions.push(pos);
+            first_steps.entry(pos).or_insert(idx + 1);
+        }
+        WireIndex {
+            positions,
+            first_steps,
+        }
+    }
+
+    /// The number of steps taken to first reach the position, if the path visits it
+    pub fn first_step(&self, pos: PathPosition) -> Option<usize> {
+        self.first_steps.get(&pos).cloned()
+    }
+
+    /// The position after the given number of steps, if the path is that long
+    pub fn position_at(&self, step: usize) -> Option<PathPosition> {
+        self.positions.get(step).cloned()
+    }
+
+    /// The total number of steps along the path
+    pub fn len(&self) -> usize {
+        self.positions.len() - 1
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
 pub fn compute_min_total_steps(
-    intersections: &Vec<PathPosition>,
+    intersections: &[PathPosition],
     p1: &Vec<Movement>,
     p2: &Vec<Movement>,
 ) -> Option<i32> {
+    let (idx1, idx2) = (WireIndex::new(p1), WireIndex::new(p2));
     intersections
-        .into_iter()
-        .map(|p| {
-            let p1_pos = p1
-                .iterator()
-                .enumerate()
-                .find(|(_idx, comp)| comp == p)
-                .unwrap()
-                .0;
-            let p2_pos = p2
-                .iterator()
-                .enumerate()
-                .find(|(_idx, comp)| comp == p)
-                .unwrap()
-                .0;
-            // add 2 since the index is the length of the path traversed minus 1.
-            (p1_pos + p2_pos + 2) as i32
-        })
+        .iter()
+        .map(|&p| (idx1.first_step(p).unwrap() + idx2.first_step(p).unwrap()) as i32)
         .min()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use proptest::prelude::*;
+    use std::collections::BTreeSet;
+
+    /// The original intersection, expanding both paths into every cell they visit
+    fn intersect_cells<P: Path>(p1: &P, p2: &P) -> Vec<PathPosition> {
+        let p1: BTreeSet<_> = p1.iterator().collect();
+        let p2: BTreeSet<_> = p2.iterator().collect();
+
+        p1.intersection(&p2).cloned().collect()
+    }
+
+    /// The number of steps to first reach a position, by walking the path cell by cell
+    fn first_step_by_cells(path: &Vec<Movement>, pos: PathPosition) -> usize {
+        path.iterator().position(|p| p == pos).unwrap() + 1
+    }
+
+    fn arb_path() -> impl Strategy<Value = Vec<Movement>> {
+        let dir = prop::sample::select(vec![
+            Direction::Up,
+            Direction::Down,
+            Direction::Left,
+            Direction::Right,
+        ]);
+        prop::collection::vec(
+            (dir, 1..8_usize).prop_map(|(dir, amount)| Movement::new(dir, amount)),
+            0..12,
+        )
+    }
+
+    proptest! {
+        #[test]
+        fn wire_crossings_match_cells(p1 in arb_path(), p2 in arb_path()) {
+            let crossings = wire_crossings(&p1.wire(), &p2.wire());
+            let positions: Vec<_> = crossings.iter().map(|c| c.pos).collect();
+            prop_assert_eq!(intersect_cells(&p1, &p2), positions);
+            for c in crossings {
+                prop_assert_eq!(
+                    (first_step_by_cells(&p1, c.pos), first_step_by_cells(&p2, c.pos)),
+                    c.steps
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_wire_segments() {
+        let wire = parse_path("R8, U5, L5, D3").unwrap().wire();
+        assert_eq!(4, wire.segments().len());
+        assert_eq!(PathPosition(0, 8), wire.segments()[0].end());
+        assert_eq!(PathPosition(0, 8), wire.segments()[1].start());
+        assert_eq!(PathPosition(2, 3), wire.segments()[3].end());
+        assert_eq!(13, wire.segments()[2].start_step);
+    }
+
+    #[test]
+    fn test_collinear_crossings() {
+        // overlapping runs in opposite directions, and a wire returning to the origin
+        let p1 = parse_path("R5").unwrap();
+        let p2 = parse_path("U1, R7, D1, L4").unwrap();
+        assert_eq!(intersect_cells(&p1, &p2), intersect(&p1, &p2));
+        assert_eq!(
+            vec![
+                Crossing {
+                    pos: PathPosition(0, 3),
+                    steps: (3, 13)
+                },
+                Crossing {
+                    pos: PathPosition(0, 4),
+                    steps: (4, 12)
+                },
+                Crossing {
+                    pos: PathPosition(0, 5),
+                    steps: (5, 11)
+                },
+            ],
+            wire_crossings(&p1.wire(), &p2.wire())
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn simplify_removes_loops(path in arb_path()) {
+            let simple = path.simplify();
+            prop_assert!(simple.self_intersections().is_empty());
+            prop_assert!(simple.loops().is_empty());
+            prop_assert!(simple.path_length() <= path.path_length());
+            prop_assert_eq!(
+                path.iterator().last().unwrap_or(PathPosition(0, 0)),
+                simple.iterator().last().unwrap_or(PathPosition(0, 0))
+            );
+            if path.self_intersections().is_empty() {
+                prop_assert_eq!(path.path_length(), simple.path_length());
+            }
+        }
+    }
+
+    #[test]
+    fn test_self_intersections() {
+        // a square loop back to (0, 2), then back along the first movement to the origin
+        let path = parse_path("R4, U2, L2, D2, L2").unwrap();
+        assert_eq!(
+            vec![
+                Revisit {
+                    pos: PathPosition(0, 0),
+                    steps: vec![0, 12]
+                },
+                Revisit {
+                    pos: PathPosition(0, 1),
+                    steps: vec![1, 11]
+                },
+                Revisit {
+                    pos: PathPosition(0, 2),
+                    steps: vec![2, 10]
+                },
+            ],
+            path.self_intersections()
+        );
+        assert_eq!(
+            vec![
+                Loop {
+                    pos: PathPosition(0, 2),
+                    start_step: 2,
+                    end_step: 10
+                },
+                Loop {
+                    pos: PathPosition(0, 1),
+                    start_step: 1,
+                    end_step: 11
+                },
+                Loop {
+                    pos: PathPosition(0, 0),
+                    start_step: 0,
+                    end_step: 12
+                },
+            ],
+            path.loops()
+        );
+        assert_eq!(8, path.loops()[0].len());
+        assert!(parse_path("R8, U5, L5, D3")
+            .unwrap()
+            .self_intersections()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_simplify() {
+        assert_eq!(
+            Vec::<Movement>::new(),
+            parse_path("R4, U2, L2, D2, L2").unwrap().simplify()
+        );
+        assert_eq!(
+            parse_path("R2, U3").unwrap(),
+            parse_path("R4, U2, L2, D2, U3").unwrap().simplify()
+        );
+        // crossing, rather than touching, also forms a loop
+        assert_eq!(
+            parse_path("U1, L1").unwrap(),
+            parse_path("R2, U2, L1, D3, L1, U2, L1").unwrap().simplify()
+        );
+
+        let (p1, _) = get_paths();
+        let simple = p1.simplify();
+        assert!(simple.path_length() < p1.path_length());
+        assert!(simple.self_intersections().is_empty());
+    }
+
+    #[test]
+    fn test_wire_index() {
+        let path = parse_path("R4, U2, L2, D3").unwrap();
+        let index = WireIndex::new(&path);
+        assert_eq!(11, index.len());
+        assert!(!index.is_empty());
+        assert_eq!(Some(PathPosition(0, 0)), index.position_at(0));
+        assert_eq!(Some(PathPosition(0, 4)), index.position_at(4));
+        assert_eq!(Some(PathPosition(-1, 2)), index.position_at(11));
+        assert_eq!(None, index.position_at(12));
+
+        // (0, 2) is visited at steps 2 and 10
+        assert_eq!(Some(2), index.first_step(PathPosition(0, 2)));
+        assert_eq!(Some(11), index.first_step(PathPosition(-1, 2)));
+        assert_eq!(None, index.first_step(PathPosition(0, 0)));
+        assert_eq!(None, index.first_step(PathPosition(5, 5)));
+
+        assert!(WireIndex::new(&Vec::new()).is_empty());
+    }
+
+    proptest! {
+        #[test]
+        fn wire_index_matches_iterator(path in arb_path()) {
+            let index = WireIndex::new(&path);
+            prop_assert_eq!(path.path_length(), index.len());
+            for (idx, pos) in path.iterator().enumerate() {
+                prop_assert_eq!(Some(pos), index.position_at(idx + 1));
+                prop_assert_eq!(Some(first_step_by_cells(&path, pos)), index.first_step(pos));
+            }
+        }
+    }
+
+    #[test]
+    fn test_circuit() {
+        let circuit = Circuit::new(&[
+            parse_path("R8, U5, L5, D3").unwrap(),
+            parse_path("U7, R6, D4, L4").unwrap(),
+            parse_path("D1, R3, U10").unwrap(),
+        ]);
+        assert_eq!(3, circuit.wires().len());
+
+        // the third wire runs up through (3, 3), where the first two cross
+        let all_three: Vec<_> = circuit.junctions_of_at_least(3).collect();
+        assert_eq!(1, all_three.len());
+        assert_eq!(PathPosition(3, 3), all_three[0].pos);
+        assert_eq!(
+            vec![(0, 20), (1, 20), (2, 8)],
+            all_three[0]
+                .steps
+                .iter()
+                .map(|(&w, &s)| (w, s))
+                .collect::<Vec<_>>()
+        );
+        assert!(circuit.junctions_of_at_least(4).next().is_none());
+
+        let closest = |i, j, metric| {
+            circuit
+                .closest_crossing(i, j, metric)
+                .map(|(junction, d)| (junction.pos, d))
+        };
+        assert_eq!(
+            Some((PathPosition(3, 3), 6)),
+            closest(0, 1, Metric::Manhattan)
+        );
+        assert_eq!(Some((PathPosition(5, 6), 30)), closest(0, 1, Metric::Steps));
+        assert_eq!(
+            Some((PathPosition(3, 3), 6)),
+            closest(1, 2, Metric::Manhattan)
+        );
+        assert_eq!(Some((PathPosition(7, 3), 22)), closest(1, 2, Metric::Steps));
+        assert_eq!(
+            Some((PathPosition(0, 3), 3)),
+            closest(0, 2, Metric::Manhattan)
+        );
+        assert_eq!(
+            circuit.junctions().len(),
+            circuit.junctions_of_at_least(2).count()
+        );
+        assert_eq!(None, closest(0, 0, Metric::Steps));
+        assert_eq!(None, closest(0, 3, Metric::Steps));
+    }
+
+    #[test]
+    fn test_circuit_matches_pairwise() {
+        let (p1, p2) = get_paths();
+        let circuit = Circuit::new(&[p1.clone(), p2.clone()]);
+        let intersections = intersect(&p1, &p2);
+        assert_eq!(
+            intersections,
+            circuit
+                .crossings_of(0, 1)
+                .map(|j| j.pos)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            compute_min_manhattan_distance(&intersections).map(|d| d as usize),
+            circuit
+                .closest_crossing(0, 1, Metric::Manhattan)
+                .map(|(_, d)| d)
+        );
+        assert_eq!(
+            Some(107754),
+            circuit
+                .closest_crossing(1, 0, Metric::Steps)
+                .map(|(_, d)| d)
+        );
+    }
+
+    #[test]
+    fn test_intersect_matches_cells_for_input() {
+        let (p1, p2) = get_paths();
+        assert_eq!(intersect_cells(&p1, &p2), intersect(&p1, &p2));
+    }
 
     #[test]
     fn test_parse_paths() {
@@ -205,16 +977,55 @@ mod tests {
         assert_eq!(Movement::new(Direction::Down, 951), p2[1]);
     }
 
+    #[test]
+    fn test_parse_path_formats() {
+        let expected = parse_path("R8, U5, L5, D3").unwrap();
+        assert_eq!(Ok(expected.clone()), parse_path("R8,U5,L5,D3"));
+        assert_eq!(Ok(expected.clone()), parse_path("r8, u5,l5 , d3"));
+        assert_eq!(Ok(expected.clone()), parse_path("  R 8,\tU5,  L5,D 3  "));
+
+        let wires = parse_wires("R8,U5,L5,D3\n\nU7,R6,D4,L4\n").unwrap();
+        assert_eq!(vec![expected, parse_path("U7,R6,D4,L4").unwrap()], wires);
+    }
+
+    #[test]
+    fn test_parse_path_errors() {
+        let err = |s| parse_path(s).unwrap_err();
+        assert_eq!(
+            ParsePathError {
+                wire: 0,
+                index: 1,
+                token: "X5".to_string(),
+                reason: "unexpected direction 'X'".to_string()
+            },
+            err("R8, X5")
+        );
+        assert_eq!("empty token", err("R8,,U5").reason);
+        assert_eq!("empty token", err("").reason);
+        assert_eq!("missing distance", err("R").reason);
+        assert_eq!("invalid distance '5x'", err("U5x").reason);
+        assert_eq!("invalid distance '-5'", err("U-5").reason);
+        assert_eq!("distance must be positive", err("U0").reason);
+        assert_eq!("distance is too large", err("U3000000000").reason);
+        assert_eq!(
+            "wire 0, token 2 'Q 1': unexpected direction 'Q'",
+            err("R1,U1,Q 1").to_string()
+        );
+
+        let err = parse_wires("R8,U5\nU7,R6,D\n").unwrap_err();
+        assert_eq!((1, 2, "D"), (err.wire, err.index, err.token.as_str()));
+    }
+
     #[test]
     fn test_path_length() {
-        let p1 = parse_path("U5");
+        let p1 = parse_path("U5").unwrap();
         assert_eq!(5, p1.iterator().into_iter().count());
     }
 
     #[test]
     fn test_example_0() {
-        let p1 = parse_path("R8, U5, L5, D3");
-        let p2 = parse_path("U7, R6, D4, L4");
+        let p1 = parse_path("R8, U5, L5, D3").unwrap();
+        let p2 = parse_path("U7, R6, D4, L4").unwrap();
         let intersections = intersect(&p1, &p2);
         assert_eq!(compute_min_manhattan_distance(&intersections).unwrap(), 6);
         assert_eq!(
@@ -225,8 +1036,8 @@ mod tests {
 
     #[test]
     fn test_example_1() {
-        let p1 = parse_path("R75,D30,R83,U83,L12,D49,R71,U7,L72");
-        let p2 = parse_path("U62,R66,U55,R34,D71,R55,D58,R83");
+        let p1 = parse_path("R75,D30,R83,U83,L12,D49,R71,U7,L72").unwrap();
+        let p2 = parse_path("U62,R66,U55,R34,D71,R55,D58,R83").unwrap();
         let intersections = intersect(&p1, &p2);
         assert_eq!(159, compute_min_manhattan_distance(&intersections).unwrap());
         assert_eq!(
@@ -237,13 +1048,13 @@ mod tests {
 
     #[test]
     fn test_example_2() {
-        let p1 = parse_path("R98,U47,R26,D63,R33,U87,L62,D20,R33,U53,R51");
+        let p1 = parse_path("R98,U47,R26,D63,R33,U87,L62,D20,R33,U53,R51").unwrap();
         assert_eq!(
             98 + 47 + 26 + 63 + 33 + 87 + 62 + 20 + 33 + 53 + 51,
             p1.path_length()
         );
 
-        let p2 = parse_path("U98,R91,D20,R16,D67,R40,U7,R15,U6,R7");
+        let p2 = parse_path("U98,R91,D20,R16,D67,R40,U7,R15,U6,R7").unwrap();
         let intersections = intersect(&p1, &p2);
         assert_eq!(135, compute_min_manhattan_distance(&intersections).unwrap());
         assert_eq!(
@@ -1,3 +1,8 @@
+use std::collections::HashMap;
+use std::ops::Range;
+
+/// The original single-password check, kept as a test oracle for `count_passwords`
+#[cfg(test)]
 fn is_valid_password(pw: u32, allow_larger_groups: bool) -> bool {
     let pw: Vec<char> = pw.to_string().chars().collect();
     if pw.len() != 6 {
@@ -35,27 +40,237 @@ fn is_valid_password(pw: u32, allow_larger_groups: bool) -> bool {
     adjacency_condition && non_decreasing
 }
 
-pub fn password_search_size() -> (u32, u32) {
-    let mut count_allowing_larger_groups = 0;
-    let mut count_requiring_exact_group_of_2 = 0;
-    for pot in 273025..767253 {
-        if is_valid_password(pot, true) {
-            count_allowing_larger_groups += 1;
+/// What's been seen so far while scanning a password's digits from left to right, assuming the
+/// digits are non-decreasing
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+struct GroupState {
+    last: u8,
+
+    /// Length of the current group of repeated digits, capped at 3 since longer groups behave
+    /// the same.  Zero before the first digit.
+    run: u8,
+
+    /// Whether a completed group had at least two digits
+    has_group: bool,
+
+    /// Whether a completed group had exactly two digits
+    has_pair: bool,
+}
+
+impl GroupState {
+    fn new() -> GroupState {
+        GroupState {
+            last: 0,
+            run: 0,
+            has_group: false,
+            has_pair: false,
         }
+    }
+
+    /// The smallest digit that can come next.  Passwords have no leading zeros.
+    fn min_next(&self) -> u8 {
+        if self.run == 0 {
+            1
+        } else {
+            self.last
+        }
+    }
+
+    fn push(mut self, digit: u8) -> GroupState {
+        if self.run > 0 && digit == self.last {
+            self.run = (self.run + 1).min(3);
+        } else {
+            self = self.end_group();
+            self.run = 1;
+        }
+        self.last = digit;
+        self
+    }
+
+    fn end_group(mut self) -> GroupState {
+        self.has_group |= self.run >= 2;
+        self.has_pair |= self.run == 2;
+        self
+    }
+
+    /// Whether the password is valid when larger groups are allowed, and when an exact pair is
+    /// required
+    fn counts(self) -> (u64, u64) {
+        let end = self.end_group();
+        (end.has_group as u64, end.has_pair as u64)
+    }
+}
+
+/// Counts the non-decreasing completions of a password with `remaining` digits left to choose,
+/// under both adjacency rules
+fn count_completions(
+    remaining: u32,
+    state: GroupState,
+    memo: &mut HashMap<(u32, GroupState), (u64, u64)>,
+) -> (u64, u64) {
+    if remaining == 0 {
+        return state.counts();
+    }
+    if let Some(&counts) = memo.get(&(remaining, state)) {
+        return counts;
+    }
+    let mut counts = (0, 0);
+    for digit in state.min_next()..=9 {
+        let (larger, exact) = count_completions(remaining - 1, state.push(digit), memo);
+        counts = (counts.0 + larger, counts.1 + exact);
+    }
+    memo.insert((remaining, state), counts);
+    counts
+}
+
+/// Counts the valid passwords with exactly `digits` digits that are less than `n`
+fn count_below(
+    n: u64,
+    digits: u32,
+    memo: &mut HashMap<(u32, GroupState), (u64, u64)>,
+) -> (u64, u64) {
+    if digits == 0 || digits > 20 {
+        return (0, 0);
+    }
+    let smallest = 10_u128.pow(digits - 1);
+    let limit = 10_u128.pow(digits);
+    if (n as u128) <= smallest {
+        return (0, 0);
+    }
+    if (n as u128) >= limit {
+        return count_completions(digits, GroupState::new(), memo);
+    }
 
-        if is_valid_password(pot, false) {
-            count_requiring_exact_group_of_2 += 1;
+    // count the passwords that match a prefix of n, then have a smaller digit, then anything
+    let n_digits: Vec<u8> = n.to_string().bytes().map(|b| b - b'0').collect();
+    let mut counts = (0, 0);
+    let mut state = GroupState::new();
+    for (idx, &digit) in n_digits.iter().enumerate() {
+        let remaining = digits - idx as u32 - 1;
+        for smaller in state.min_next()..digit {
+            let (larger, exact) = count_completions(remaining, state.push(smaller), memo);
+            counts = (counts.0 + larger, counts.1 + exact);
+        }
+        if digit < state.min_next() {
+            break;
         }
+        state = state.push(digit);
+    }
+    counts
+}
+
+/// Counts the valid passwords with exactly `digits` digits in the range, when larger groups are
+/// allowed and when an exact group of two is required.  Rather than checking every number, this
+/// counts the non-decreasing digit sequences below each end of the range, digit by digit, so it
+/// takes time proportional to the number of digits rather than the size of the range.
+pub fn count_passwords(range: Range<u64>, digits: u32) -> (u64, u64) {
+    if range.start >= range.end {
+        return (0, 0);
     }
-    (
-        count_allowing_larger_groups,
-        count_requiring_exact_group_of_2,
-    )
+    let mut memo = HashMap::new();
+    let below_end = count_below(range.end, digits, &mut memo);
+    let below_start = count_below(range.start, digits, &mut memo);
+    (below_end.0 - below_start.0, below_end.1 - below_start.1)
+}
+
+/// The range of passwords from the puzzle input
+pub const PUZZLE_RANGE: Range<u64> = 273025..767253;
+
+pub fn password_search_size(range: Range<u64>) -> (u32, u32) {
+    let (larger, exact) = count_passwords(range, 6);
+    (larger as u32, exact as u32)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use proptest::prelude::*;
+
+    /// The original brute force search
+    fn brute_force_search_size(range: Range<u32>) -> (u64, u64) {
+        let mut count_allowing_larger_groups = 0;
+        let mut count_requiring_exact_group_of_2 = 0;
+        for pot in range {
+            if is_valid_password(pot, true) {
+                count_allowing_larger_groups += 1;
+            }
+
+            if is_valid_password(pot, false) {
+                count_requiring_exact_group_of_2 += 1;
+            }
+        }
+        (
+            count_allowing_larger_groups,
+            count_requiring_exact_group_of_2,
+        )
+    }
+
+    /// Brute force for any number of digits, checking the group lengths directly
+    fn brute_force_any_length(range: Range<u64>, digits: usize) -> (u64, u64) {
+        let mut counts = (0, 0);
+        for n in range {
+            let s = n.to_string().into_bytes();
+            if s.len() != digits || s.windows(2).any(|w| w[0] > w[1]) {
+                continue;
+            }
+            let mut groups = Vec::new();
+            let mut start = 0;
+            for i in 1..=s.len() {
+                if i == s.len() || s[i] != s[start] {
+                    groups.push(i - start);
+                    start = i;
+                }
+            }
+            counts.0 += groups.iter().any(|&g| g >= 2) as u64;
+            counts.1 += groups.contains(&2) as u64;
+        }
+        counts
+    }
+
+    proptest! {
+        #[test]
+        fn count_matches_brute_force(start in 90_000..1_010_000_u32, len in 0..5_000_u32) {
+            prop_assert_eq!(
+                brute_force_search_size(start..start + len),
+                count_passwords(start as u64..(start + len) as u64, 6)
+            );
+        }
+    }
+
+    #[test]
+    fn test_count_matches_brute_force() {
+        assert_eq!(
+            brute_force_search_size(273025..767253),
+            count_passwords(PUZZLE_RANGE, 6)
+        );
+        assert_eq!(
+            brute_force_search_size(0..1_000_000),
+            count_passwords(0..u64::MAX, 6)
+        );
+        for digits in 1..=5 {
+            assert_eq!(
+                brute_force_any_length(0..100_000, digits as usize),
+                count_passwords(0..100_000, digits)
+            );
+        }
+        assert_eq!(
+            brute_force_any_length(1_234_567..2_345_678, 7),
+            count_passwords(1_234_567..2_345_678, 7)
+        );
+    }
+
+    #[test]
+    fn test_count_edge_cases() {
+        let (lo, hi) = (500, 100);
+        assert_eq!((0, 0), count_passwords(lo..hi, 3));
+        assert_eq!((0, 0), count_passwords(0..u64::MAX, 0));
+        assert_eq!((1, 1), count_passwords(11..12, 2));
+        assert_eq!((0, 0), count_passwords(12..13, 2));
+        // there are only so many non-decreasing sequences of 20 digits, whatever the range
+        let (larger, exact) = count_passwords(0..u64::MAX, 20);
+        assert!(larger > 0 && exact > 0 && exact <= larger);
+        assert_eq!((0, 0), count_passwords(0..u64::MAX, 21));
+    }
 
     #[test]
     fn test_1() {
@@ -77,7 +292,7 @@ mod tests {
 
     #[test]
     fn answer() {
-        let (p1, p2) = password_search_size();
+        let (p1, p2) = password_search_size(PUZZLE_RANGE);
         assert_eq!(910, p1);
         assert_eq!(598, p2);
     }
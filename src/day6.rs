@@ -1,34 +1,483 @@
+use crate::orbit_render::{self, TreeOptions};
 use std::collections::btree_map::BTreeMap;
 use std::collections::btree_set::BTreeSet;
+use std::fmt;
+use std::fs;
+use std::path::Path;
 
-type Body = String;
+pub type Body = String;
 
-struct OrbitTree {
-    root: Body,
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct OrbitMapError {
+    /// 1-based line number of the problem
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for OrbitMapError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+/// Which bodies an orbit map is rooted at
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Roots {
+    /// Every body must orbit one of these bodies, directly or indirectly
+    Named(Vec<Body>),
+
+    /// Every body that doesn't orbit anything is a root, so the map can be a forest of separate
+    /// trees, for example after merging maps from several sources
+    Detect,
+}
+
+/// Splits a line of the form `A)B` into the body being orbited and the body orbiting it
+fn parse_orbit(line: &str) -> Option<(&str, &str)> {
+    let mut parts = line.trim().split(')');
+    let (center, obj) = (parts.next()?, parts.next()?);
+    let valid = |name: &str| !name.is_empty() && !name.contains(char::is_whitespace);
+    if parts.next().is_some() || !valid(center) || !valid(obj) {
+        return None;
+    }
+    Some((center, obj))
+}
+
+pub struct OrbitTree {
+    /// The bodies that don't orbit anything, one for each tree in the map
+    roots: Vec<Body>,
     bodies: BTreeSet<Body>,
     parents: BTreeMap<Body, Body>,
+
+    /// The bodies directly orbiting each body, and each body's depth and subtree size.  These
+    /// are filled in once the whole map has been read.
+    children: BTreeMap<Body, Vec<Body>>,
+    depths: BTreeMap<Body, usize>,
+    subtree_sizes: BTreeMap<Body, usize>,
+
+    /// Bodies numbered in breadth first order, for finding common ancestors by binary lifting:
+    /// `ancestors[k][id]` is the id of the body 2^k orbits further in, or of the root if there
+    /// aren't that many
+    ids: BTreeMap<Body, usize>,
+    names: Vec<Body>,
+    ancestors: Vec<Vec<usize>>,
 }
 
 impl OrbitTree {
-    fn new() -> Self {
+    fn new(roots: Vec<Body>) -> Self {
         OrbitTree {
-            root: "COM".to_string(),
+            roots,
             bodies: BTreeSet::new(),
             parents: BTreeMap::new(),
+            children: BTreeMap::new(),
+            depths: BTreeMap::new(),
+            subtree_sizes: BTreeMap::new(),
+            ids: BTreeMap::new(),
+            names: Vec::new(),
+            ancestors: Vec::new(),
         }
     }
 
     fn from_string_arr(orbits: &[&str]) -> OrbitTree {
-        let mut orbit_tree = OrbitTree::new();
+        OrbitTree::parse(&orbits.join("\n")).unwrap_or_else(|errors| {
+            let errors: Vec<_> = errors.iter().map(|e| e.to_string()).collect();
+            panic!("Invalid orbit map:\n{}", errors.join("\n"))
+        })
+    }
+
+    /// Parses an orbit map rooted at COM
+    pub fn parse(contents: &str) -> Result<OrbitTree, Vec<OrbitMapError>> {
+        OrbitTree::parse_with_roots(contents, &Roots::Named(vec!["COM".to_string()]))
+    }
+
+    /// Parses an orbit map with one `A)B` orbit per line, meaning that B orbits A.  Blank lines
+    /// are ignored.  The map is validated in full, and every malformed line, body with two
+    /// parents, cycle and body not connected to a root is reported with its line number.
+    pub fn parse_with_roots(
+        contents: &str,
+        roots: &Roots,
+    ) -> Result<OrbitTree, Vec<OrbitMapError>> {
+        let mut orbit_tree = OrbitTree::new(match roots {
+            Roots::Named(roots) => roots.clone(),
+            Roots::Detect => Vec::new(),
+        });
+        let mut errors = Vec::new();
+        // the line each body's orbit is declared on, and the first line each body appears on
+        let mut declared = BTreeMap::new();
+        let mut first_seen = BTreeMap::new();
+
+        for (idx, line) in contents.lines().enumerate() {
+            let line_num = idx + 1;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let (center, obj) = match parse_orbit(line) {
+                Some(orbit) => orbit,
+                None => {
+                    errors.push(OrbitMapError {
+                        line: line_num,
+                        message: format!("Expected an orbit like A)B, found '{}'", line.trim()),
+                    });
+                    continue;
+                }
+            };
+            first_seen.entry(center.to_string()).or_insert(line_num);
+            first_seen.entry(obj.to_string()).or_insert(line_num);
 
-        for orbit in orbits {
-            let idx = orbit.find(')').expect("Invalid orbit specification");
-            let (left, right) = orbit.split_at(idx);
-            let right = &right[1..];
+            match orbit_tree.parents.get(obj) {
+                Some(parent) if parent != center => errors.push(OrbitMapError {
+                    line: line_num,
+                    message: format!(
+                        "{} already orbits {} (line {}), so it can't also orbit {}",
+                        obj, parent, declared[obj], center
+                    ),
+                }),
+                Some(_) => {}
+                None => {
+                    declared.insert(obj.to_string(), line_num);
+                    orbit_tree.object_in_orbit_around(obj.to_string(), center.to_string());
+                }
+            }
+        }
 
-            orbit_tree.object_in_orbit_around(right.to_string(), left.to_string());
+        errors.extend(orbit_tree.find_cycles(&declared));
+        match roots {
+            Roots::Named(roots) => {
+                for root in roots {
+                    if let Some(parent) = orbit_tree.parents.get(root) {
+                        errors.push(OrbitMapError {
+                            line: declared[root],
+                            message: format!("{} should be a root, but orbits {}", root, parent),
+                        });
+                    }
+                }
+                errors.extend(orbit_tree.find_disconnected(&first_seen));
+            }
+            Roots::Detect => {
+                orbit_tree.roots = orbit_tree
+                    .bodies
+                    .iter()
+                    .filter(|b| !orbit_tree.parents.contains_key(*b))
+                    .cloned()
+                    .collect();
+            }
+        }
+        if errors.is_empty() {
+            orbit_tree.index();
+            Ok(orbit_tree)
+        } else {
+            errors.sort_by_key(|e| e.line);
+            Err(errors)
         }
-        orbit_tree
+    }
+
+    /// Reads and parses an orbit map file
+    pub fn load(path: &Path) -> Result<OrbitTree, String> {
+        let contents = fs::read_to_string(path)
+            .map_err(|e| format!("Unable to read {}: {}", path.display(), e))?;
+        OrbitTree::parse(&contents).map_err(|errors| {
+            errors
+                .iter()
+                .map(|e| format!("{}:{}: {}", path.display(), e.line, e.message))
+                .collect::<Vec<_>>()
+                .join("\n")
+        })
+    }
+
+    /// Reports each cycle of orbits once, at the earliest line that declares one of its orbits
+    fn find_cycles(&self, declared: &BTreeMap<Body, usize>) -> Vec<OrbitMapError> {
+        let mut errors = Vec::new();
+        let mut visited = BTreeSet::new();
+        for body in &self.bodies {
+            // walk towards the root until reaching somewhere already visited
+            let mut walk = Vec::new();
+            let mut curr = Some(body);
+            while let Some(b) = curr {
+                if !visited.insert(b) {
+                    break;
+                }
+                walk.push(b);
+                curr = self.parents.get(b);
+            }
+            // if the walk ran into itself, the part from that body on is a cycle
+            if let Some(start) = curr.and_then(|c| walk.iter().position(|&b| b == c)) {
+                let cycle = &walk[start..];
+                let first = *cycle.iter().min_by_key(|&&b| declared[b]).unwrap();
+                // list the bodies outwards, as orbits are written, starting with the earliest
+                let center = &self.parents[first];
+                let mut chain = vec![center];
+                let mut b = center;
+                loop {
+                    b = &self.parents[b];
+                    if b == center {
+                        break;
+                    }
+                    chain.push(b);
+                }
+                chain.reverse();
+                chain.rotate_right(1);
+                chain.push(center);
+                let chain: Vec<&str> = chain.iter().map(|b| b.as_str()).collect();
+                errors.push(OrbitMapError {
+                    line: declared[first],
+                    message: format!("Orbits form a cycle: {}", chain.join(")")),
+                });
+            }
+        }
+        errors
+    }
+
+    /// Reports each body that doesn't orbit anything, other than the root, at the first line it
+    /// appears on
+    fn find_disconnected(&self, first_seen: &BTreeMap<Body, usize>) -> Vec<OrbitMapError> {
+        // the body without a parent that each body ends up orbiting.  Bodies in or below a cycle
+        // never reach one.  Each body is only walked past once, so this is linear in the size of
+        // the map however deep it is.
+        let mut tops: BTreeMap<&Body, Option<&Body>> = BTreeMap::new();
+        for body in &self.bodies {
+            let mut walk = Vec::new();
+            let mut on_walk = BTreeSet::new();
+            let mut curr = body;
+            let top = loop {
+                if let Some(&top) = tops.get(curr) {
+                    break top;
+                }
+                if !on_walk.insert(curr) {
+                    break None;
+                }
+                walk.push(curr);
+                match self.parents.get(curr) {
+                    Some(parent) => curr = parent,
+                    None => break Some(curr),
+                }
+            };
+            for b in walk {
+                tops.insert(b, top);
+            }
+        }
+
+        let mut orbiting = BTreeMap::new();
+        for (&body, &top) in &tops {
+            if let Some(top) = top {
+                *orbiting.entry(top).or_insert(0) += if top == body { 0 } else { 1 };
+            }
+        }
+        orbiting
+            .into_iter()
+            .filter(|&(top, _)| !self.roots.contains(top))
+            .map(|(top, count)| {
+                let orbiting = match count {
+                    1 => "neither is the 1 body".to_string(),
+                    count => format!("neither are the {} bodies", count),
+                };
+                OrbitMapError {
+                    line: first_seen[top],
+                    message: format!(
+                        "{} is not connected to {}, and {} orbiting it",
+                        top,
+                        self.roots.join(" or "),
+                        orbiting
+                    ),
+                }
+            })
+            .collect()
+    }
+
+    /// Computes the children, depth and subtree size of every body, working outwards from the
+    /// root and then back in, so that each body is only visited a constant number of times
+    fn index(&mut self) {
+        for (obj, center) in &self.parents {
+            self.children
+                .entry(center.clone())
+                .or_default()
+                .push(obj.clone());
+        }
+
+        // breadth first, so every body comes after the body it orbits
+        let mut order = Vec::new();
+        // named roots that don't appear in the map have nothing to index
+        let bodies = &self.bodies;
+        self.roots.retain(|r| bodies.contains(r));
+        for root in &self.roots {
+            self.depths.insert(root.clone(), 0);
+            order.push(root);
+        }
+        let mut idx = 0;
+        while idx < order.len() {
+            let body = order[idx];
+            let depth = self.depths[body];
+            for child in self.children.get(body).into_iter().flatten() {
+                self.depths.insert(child.clone(), depth + 1);
+                order.push(child);
+            }
+            idx += 1;
+        }
+
+        for &body in order.iter().rev() {
+            let size = 1 + self
+                .children
+                .get(body)
+                .into_iter()
+                .flatten()
+                .map(|child| self.subtree_sizes[child])
+                .sum::<usize>();
+            self.subtree_sizes.insert(body.clone(), size);
+        }
+
+        self.names = order.into_iter().cloned().collect();
+        self.ids = self
+            .names
+            .iter()
+            .enumerate()
+            .map(|(id, name)| (name.clone(), id))
+            .collect();
+        let parent_ids: Vec<usize> = self
+            .names
+            .iter()
+            .enumerate()
+            .map(|(id, name)| self.parents.get(name).map_or(id, |p| self.ids[p]))
+            .collect();
+        let levels = (usize::BITS - self.names.len().leading_zeros()).max(1);
+        self.ancestors = vec![parent_ids];
+        for k in 1..levels as usize {
+            let prev = &self.ancestors[k - 1];
+            let next = prev.iter().map(|&a| prev[a]).collect();
+            self.ancestors.push(next);
+        }
+    }
+
+    fn id_depth(&self, id: usize) -> usize {
+        self.depths[&self.names[id]]
+    }
+
+    /// The id of the body `steps` orbits further in than the body with the given id
+    fn lift(&self, mut id: usize, steps: usize) -> usize {
+        for (k, ancestors) in self.ancestors.iter().enumerate() {
+            if steps & (1 << k) != 0 {
+                id = ancestors[id];
+            }
+        }
+        id
+    }
+
+    fn lca_id(&self, a: usize, b: usize) -> Option<usize> {
+        let (mut a, mut b) = if self.id_depth(a) >= self.id_depth(b) {
+            (a, b)
+        } else {
+            (b, a)
+        };
+        a = self.lift(a, self.id_depth(a) - self.id_depth(b));
+        if a == b {
+            return Some(a);
+        }
+        for ancestors in self.ancestors.iter().rev() {
+            if ancestors[a] != ancestors[b] {
+                a = ancestors[a];
+                b = ancestors[b];
+            }
+        }
+        // bodies that reach different roots have no common ancestor
+        let (pa, pb) = (self.ancestors[0][a], self.ancestors[0][b]);
+        if pa == pb {
+            Some(pa)
+        } else {
+            None
+        }
+    }
+
+    /// The innermost body that both bodies orbit, directly or indirectly, or are, or None if
+    /// they're in different trees.  This takes time logarithmic in the depth of the map.
+    pub fn lca(&self, a: &str, b: &str) -> Option<&Body> {
+        let id = self.lca_id(*self.ids.get(a)?, *self.ids.get(b)?)?;
+        Some(&self.names[id])
+    }
+
+    /// The number of orbits between two bodies
+    pub fn distance(&self, a: &str, b: &str) -> Option<usize> {
+        let lca = self.lca(a, b)?;
+        Some(self.depths[a] + self.depths[b] - 2 * self.depths[lca])
+    }
+
+    /// The number of orbits between each pair of bodies
+    pub fn distances(&self, pairs: &[(&str, &str)]) -> Vec<Option<usize>> {
+        pairs.iter().map(|&(a, b)| self.distance(a, b)).collect()
+    }
+
+    /// The bodies passed through moving from one body to another along orbits, including both
+    /// ends
+    pub fn transfer_path(&self, a: &str, b: &str) -> Option<Vec<&Body>> {
+        let lca = self.lca(a, b)?;
+        let climb = |from: &str| {
+            let mut path = Vec::new();
+            let mut id = self.ids[from];
+            while self.names[id] != *lca {
+                path.push(&self.names[id]);
+                id = self.ancestors[0][id];
+            }
+            path
+        };
+        let mut path = climb(a);
+        path.push(lca);
+        let mut down = climb(b);
+        down.reverse();
+        path.extend(down);
+        Some(path)
+    }
+
+    /// The bodies the map is rooted at, one for each tree in the map
+    pub fn roots(&self) -> &[Body] {
+        &self.roots
+    }
+
+    /// The root of the tree the body is in
+    pub fn root_of(&self, body: &str) -> Option<&Body> {
+        let id = *self.ids.get(body)?;
+        Some(&self.names[self.lift(id, self.id_depth(id))])
+    }
+
+    /// The total number of direct and indirect orbits in each tree, by root
+    pub fn checksums(&self) -> BTreeMap<&Body, usize> {
+        let mut checksums: BTreeMap<&Body, usize> =
+            self.roots.iter().map(|root| (root, 0)).collect();
+        for (body, depth) in &self.depths {
+            *checksums.get_mut(self.root_of(body).unwrap()).unwrap() += depth;
+        }
+        checksums
+    }
+
+    /// Whether the body appears anywhere in the map
+    pub(crate) fn contains(&self, body: &str) -> bool {
+        self.bodies.contains(body)
+    }
+
+    /// The bodies directly orbiting the body, in order
+    pub(crate) fn children(&self, body: &str) -> &[Body] {
+        self.children.get(body).map_or(&[], |c| c.as_slice())
+    }
+
+    /// The number of bodies that the body orbits, directly or indirectly
+    pub fn depth(&self, body: &str) -> Option<usize> {
+        self.depths.get(body).cloned()
+    }
+
+    /// Renders the map as an indented ASCII tree, one node per line
+    pub fn render_ascii_tree(&self, options: &TreeOptions) -> Result<String, String> {
+        orbit_render::render_ascii_tree(self, options)
+    }
+
+    /// Renders the map as a Graphviz DOT graph
+    pub fn render_dot(&self, options: &TreeOptions) -> Result<String, String> {
+        orbit_render::render_dot(self, options)
+    }
+
+    /// The number of bodies in the subtree rooted at the body, including the body itself
+    pub fn subtree_size(&self, body: &str) -> Option<usize> {
+        self.subtree_sizes.get(body).cloned()
+    }
+
+    /// The total number of direct and indirect orbits across every tree, from the depths
+    /// computed when the map was read
+    pub fn checksum(&self) -> usize {
+        self.depths.values().sum()
     }
 
     fn object_in_orbit_around(&mut self, obj: Body, center: Body) {
@@ -51,9 +500,9 @@ impl OrbitTree {
     fn num_indirect_orbits_of_body(&self, body: &Body) -> usize {
         let mut indirect_orbits = 0;
         if let Some(mut curr) = self.parents.get(body) {
-            while curr != &self.root {
+            while let Some(parent) = self.parents.get(curr) {
                 indirect_orbits += 1;
-                curr = self.parents.get(curr).unwrap();
+                curr = parent;
             }
         }
         indirect_orbits
@@ -71,37 +520,10 @@ impl OrbitTree {
         self.num_direct_orbits() + self.num_indirect_orbits()
     }
 
-    /// Computes the minimum number of orbital transfers required to move from b1 to b2
-    fn intersect(&self, b1: &Body, b2: &Body) -> usize {
-        let mut path = Vec::new();
-        let mut path_set = BTreeSet::new();
-        let mut curr = b1;
-        while let Some(b) = self.parents.get(curr) {
-            path.push(b);
-            path_set.insert(b);
-            curr = b;
-        }
-
-        let mut hops = 0;
-        let mut common = None;
-        curr = b2;
-        while let Some(b) = self.parents.get(curr) {
-            if path.contains(&b) {
-                common = Some(b);
-                break;
-            }
-            curr = b;
-            hops += 1;
-        }
-
-        let common = common.unwrap();
-        for p in path {
-            if p == common {
-                break;
-            }
-            hops += 1;
-        }
-        hops
+    /// Computes the minimum number of orbital transfers required to move from the body b1 orbits
+    /// to the body b2 orbits, or None if either doesn't orbit anything or they aren't connected
+    fn intersect(&self, b1: &Body, b2: &Body) -> Option<usize> {
+        self.distance(self.parents.get(b1)?, self.parents.get(b2)?)
     }
 }
 
@@ -133,26 +555,289 @@ mod tests {
         let mut orbit_tree = OrbitTree::from_string_arr(&orbits);
 
         let common = orbit_tree.intersect(&"YOU".to_string(), &"SAN".to_string());
-        assert_eq!(common, 4);
+        assert_eq!(common, Some(4));
+    }
+
+    #[test]
+    fn test_depths_and_subtree_sizes() {
+        let orbits = [
+            "COM)B", "B)C", "C)D", "D)E", "E)F", "B)G", "G)H", "D)I", "E)J", "J)K", "K)L",
+        ];
+        let orbit_tree = OrbitTree::from_string_arr(&orbits);
+
+        assert_eq!(Some(0), orbit_tree.depth("COM"));
+        assert_eq!(Some(3), orbit_tree.depth("D"));
+        assert_eq!(Some(7), orbit_tree.depth("L"));
+        assert_eq!(None, orbit_tree.depth("YOU"));
+
+        assert_eq!(Some(12), orbit_tree.subtree_size("COM"));
+        assert_eq!(Some(2), orbit_tree.subtree_size("G"));
+        assert_eq!(Some(5), orbit_tree.subtree_size("E"));
+        assert_eq!(Some(1), orbit_tree.subtree_size("L"));
+        assert_eq!(None, orbit_tree.subtree_size("YOU"));
+
+        assert_eq!(42, orbit_tree.checksum());
+        for body in &orbit_tree.bodies {
+            assert_eq!(
+                orbit_tree.num_indirect_orbits_of_body(body) + if body == "COM" { 0 } else { 1 },
+                orbit_tree.depth(body).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn test_checksum_of_deep_map() {
+        // a single chain this long would take billions of steps to walk from every body
+        let n = 100_000;
+        let mut map = String::from("COM)0\n");
+        for i in 1..n {
+            map += &format!("{}){}\n", i - 1, i);
+        }
+        let orbit_tree = OrbitTree::parse(&map).unwrap();
+        assert_eq!(n * (n + 1) / 2, orbit_tree.checksum());
+        assert_eq!(Some(n), orbit_tree.depth(&(n - 1).to_string()));
+        assert_eq!(Some(n + 1), orbit_tree.subtree_size("COM"));
+    }
+
+    #[test]
+    fn test_lowest_common_ancestor() {
+        let orbits = [
+            "COM)B", "B)C", "C)D", "D)E", "E)F", "B)G", "G)H", "D)I", "E)J", "J)K", "K)L", "K)YOU",
+            "I)SAN",
+        ];
+        let orbit_tree = OrbitTree::from_string_arr(&orbits);
+
+        assert_eq!(Some(&"D".to_string()), orbit_tree.lca("YOU", "SAN"));
+        assert_eq!(Some(&"D".to_string()), orbit_tree.lca("D", "L"));
+        assert_eq!(Some(&"B".to_string()), orbit_tree.lca("H", "SAN"));
+        assert_eq!(Some(&"L".to_string()), orbit_tree.lca("L", "L"));
+        assert_eq!(Some(&"COM".to_string()), orbit_tree.lca("COM", "F"));
+        assert_eq!(None, orbit_tree.lca("YOU", "Santa"));
+
+        assert_eq!(
+            Some(vec!["YOU", "K", "J", "E", "D", "I", "SAN"]),
+            orbit_tree
+                .transfer_path("YOU", "SAN")
+                .map(|path| path.iter().map(|b| b.as_str()).collect::<Vec<_>>())
+        );
+        assert_eq!(
+            Some(vec!["D", "E", "J"]),
+            orbit_tree
+                .transfer_path("D", "J")
+                .map(|path| path.iter().map(|b| b.as_str()).collect::<Vec<_>>())
+        );
+        assert_eq!(
+            Some(vec![&"H".to_string()]),
+            orbit_tree.transfer_path("H", "H")
+        );
+        assert_eq!(None, orbit_tree.transfer_path("H", "Santa"));
+
+        assert_eq!(
+            vec![Some(6), Some(0), Some(7), None],
+            orbit_tree.distances(&[("YOU", "SAN"), ("C", "C"), ("COM", "YOU"), ("X", "B")])
+        );
+        assert_eq!(
+            None,
+            orbit_tree.intersect(&"COM".to_string(), &"SAN".to_string())
+        );
+    }
+
+    #[test]
+    fn test_lca_matches_walking_to_the_root() {
+        let orbit_tree = get_local_orbits();
+        let ancestors = |body: &Body| {
+            let mut path = vec![body.clone()];
+            let mut curr = body;
+            while let Some(parent) = orbit_tree.parents.get(curr) {
+                path.push(parent.clone());
+                curr = parent;
+            }
+            path
+        };
+        let bodies: Vec<&Body> = orbit_tree.bodies.iter().collect();
+        for (i, &a) in bodies.iter().enumerate().step_by(7) {
+            let path_a = ancestors(a);
+            for &b in bodies.iter().skip(i % 13).step_by(37) {
+                let path_b = ancestors(b);
+                let expected = path_a.iter().find(|body| path_b.contains(body));
+                assert_eq!(expected, orbit_tree.lca(a, b));
+                let path = orbit_tree.transfer_path(a, b).unwrap();
+                assert_eq!(orbit_tree.distance(a, b).unwrap() + 1, path.len());
+                assert!(path
+                    .windows(2)
+                    .all(|w| orbit_tree.parents.get(w[0]) == Some(w[1])
+                        || orbit_tree.parents.get(w[1]) == Some(w[0])));
+            }
+        }
+    }
+
+    #[test]
+    fn test_forest() {
+        let map = "COM)B\nB)C\nX)Y\nY)Z\nX)W";
+        let forest = OrbitTree::parse_with_roots(map, &Roots::Detect).unwrap();
+        assert_eq!(&["COM".to_string(), "X".to_string()], forest.roots());
+        assert_eq!(Some(&"X".to_string()), forest.root_of("Z"));
+        assert_eq!(Some(&"COM".to_string()), forest.root_of("COM"));
+        assert_eq!(None, forest.root_of("Q"));
+        assert_eq!(Some(2), forest.depth("Z"));
+        assert_eq!(Some(4), forest.subtree_size("X"));
+
+        // queries that cross between trees have no answer
+        assert_eq!(Some(&"X".to_string()), forest.lca("Z", "W"));
+        assert_eq!(None, forest.lca("C", "Z"));
+        assert_eq!(
+            vec![Some(3), None],
+            forest.distances(&[("Z", "W"), ("B", "Y")])
+        );
+        assert_eq!(None, forest.transfer_path("C", "W"));
+        assert_eq!(None, forest.intersect(&"C".to_string(), &"Z".to_string()));
+
+        let checksums = forest.checksums();
+        assert_eq!(Some(&3), checksums.get(&"COM".to_string()));
+        assert_eq!(Some(&4), checksums.get(&"X".to_string()));
+        assert_eq!(7, forest.checksum());
+        assert_eq!(7, forest.num_direct_and_indirect_orbits());
+    }
+
+    #[test]
+    fn test_named_roots() {
+        let map = "COM)B\nB)C\nX)Y\nY)Z\nX)W";
+        let roots = Roots::Named(vec!["COM".to_string(), "X".to_string(), "Q".to_string()]);
+        let forest = OrbitTree::parse_with_roots(map, &roots).unwrap();
+        // roots that don't appear in the map are dropped
+        assert_eq!(&["COM".to_string(), "X".to_string()], forest.roots());
+
+        assert_eq!(
+            vec!["line 3: X is not connected to COM, and neither are the 3 bodies orbiting it"],
+            parse_errors(map)
+        );
+
+        let roots = Roots::Named(vec!["B".to_string(), "X".to_string()]);
+        let errors: Vec<_> = OrbitTree::parse_with_roots(map, &roots)
+            .err()
+            .unwrap()
+            .iter()
+            .map(|e| e.to_string())
+            .collect();
+        assert_eq!(
+            vec![
+                "line 1: B should be a root, but orbits COM",
+                "line 1: COM is not connected to B or X, and neither are the 2 bodies orbiting it",
+            ],
+            errors
+        );
+    }
+
+    fn parse_errors(contents: &str) -> Vec<String> {
+        OrbitTree::parse(contents)
+            .err()
+            .unwrap()
+            .iter()
+            .map(|e| e.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_orbit_map() {
+        let orbit_tree = OrbitTree::parse("COM)B\n B)C \n\nB)G\nC)D\n").unwrap();
+        assert_eq!(4, orbit_tree.num_direct_orbits());
+        assert_eq!(8, orbit_tree.num_direct_and_indirect_orbits());
+
+        // repeating an orbit is harmless
+        let orbit_tree = OrbitTree::parse("COM)B\nB)C\nCOM)B").unwrap();
+        assert_eq!(2, orbit_tree.num_direct_orbits());
+
+        assert_eq!(0, OrbitTree::parse("").unwrap().num_direct_orbits());
+    }
+
+    #[test]
+    fn test_parse_malformed_lines() {
+        assert_eq!(
+            vec![
+                "line 2: Expected an orbit like A)B, found 'BC'",
+                "line 3: Expected an orbit like A)B, found 'C)'",
+                "line 4: Expected an orbit like A)B, found 'C)D)E'",
+                "line 5: Expected an orbit like A)B, found 'C D)E'",
+            ],
+            parse_errors("COM)B\nBC\nC)\nC)D)E\nC D)E")
+        );
+    }
+
+    #[test]
+    fn test_parse_duplicate_parents() {
+        assert_eq!(
+            vec!["line 3: C already orbits B (line 2), so it can't also orbit COM"],
+            parse_errors("COM)B\nB)C\nCOM)C")
+        );
+    }
+
+    #[test]
+    fn test_parse_cycles() {
+        assert_eq!(
+            vec![
+                "line 2: Orbits form a cycle: A)B)C)A",
+                "line 6: Orbits form a cycle: X)X",
+            ],
+            parse_errors("COM)D\nA)B\nB)C\nC)A\nC)E\nX)X")
+        );
+    }
+
+    #[test]
+    fn test_parse_disconnected_bodies() {
+        // bodies below a cycle are only reported as part of the cycle
+        assert_eq!(
+            vec!["line 2: Orbits form a cycle: A)B)A"],
+            parse_errors("COM)C\nA)B\nB)A\nB)D")
+        );
+        assert_eq!(
+            vec![
+                "line 3: X is not connected to COM, and neither are the 2 bodies orbiting it",
+                "line 5: Q is not connected to COM, and neither is the 1 body orbiting it",
+            ],
+            parse_errors("COM)B\nB)C\nX)Y\nY)W\nQ)Z")
+        );
+    }
+
+    #[test]
+    fn test_load_orbit_map() {
+        let path = std::env::temp_dir().join("aoc_day6_orbits.txt");
+        fs::write(&path, "COM)B\nB)C\nC)D\n").unwrap();
+        let orbit_tree = OrbitTree::load(&path).unwrap();
+        assert_eq!(6, orbit_tree.num_direct_and_indirect_orbits());
+
+        fs::write(&path, "COM)B\nB\n").unwrap();
+        assert_eq!(
+            Err(format!(
+                "{}:2: Expected an orbit like A)B, found 'B'",
+                path.display()
+            )),
+            OrbitTree::load(&path).map(|_| ())
+        );
+        fs::remove_file(&path).unwrap();
     }
 
     #[test]
     fn compute_total_num_direct_and_indirect_orbits_in_map_data() {
         let orbit_tree = get_local_orbits();
         assert_eq!(106065, orbit_tree.num_direct_and_indirect_orbits());
+        assert_eq!(106065, orbit_tree.checksum());
+        assert_eq!(
+            Some(orbit_tree.bodies.len()),
+            orbit_tree.subtree_size("COM")
+        );
     }
 
     #[test]
     fn compute_num_orbital_transfers_from_you_to_san() {
         let orbit_tree = get_local_orbits();
         assert_eq!(
-            253,
+            Some(253),
             orbit_tree.intersect(&"YOU".to_string(), &"SAN".to_string())
         );
     }
 }
 
-fn get_local_orbits() -> OrbitTree {
+pub fn get_local_orbits() -> OrbitTree {
     let orbits = [
         "PJK)X3G", "ZM3)JGN", "YYF)614", "K5T)X18", "2PT)2BR", "4RF)VL2", "QQN)7S4", "X9S)HM9",
         "NMG)DH5", "TQD)SJD", "2JD)SM4", "5SD)4NG", "W1R)XYJ", "DXF)72L", "CGN)T85", "ZF4)ZZ1",
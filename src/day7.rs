@@ -111,7 +111,7 @@ fn compute_thruster_signal_with_feedback(
         amp_e.mut_input().push(amp_d.output.remove(0));
 
         match amp_e.execute().unwrap() {
-            ProgramState::Terminated(result) => return amp_e.output.remove(0),
+            ProgramState::Terminated(_) => return amp_e.output.remove(0),
             ProgramState::AwaitingInput => amp_a.mut_input().push(amp_e.output.remove(0)),
         }
     }
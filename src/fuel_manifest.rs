@@ -0,0 +1,609 @@
+/// Loads Day 1 module manifests from files supplied by other teams.
+///
+/// Three formats are supported:
+///
+/// * text - one mass per line
+/// * CSV - `name,mass` or `name,mass,count` per line, with an optional header row
+/// * JSON - an array whose elements are either masses or objects with a `name`, a `mass` and an
+///   optional `count`, e.g. `[12, {"name": "probe", "mass": 1969, "count": 2}]`
+///
+/// Manifests are validated in full, and every problem found is reported with its line number
+/// rather than stopping at the first.
+use crate::day1::Module;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::num::IntErrorKind;
+use std::path::Path;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum ManifestFormat {
+    Text,
+    Csv,
+    Json,
+}
+
+impl ManifestFormat {
+    /// Chooses the format from the file extension, defaulting to text
+    pub fn from_path(path: &Path) -> ManifestFormat {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("csv") => ManifestFormat::Csv,
+            Some("json") => ManifestFormat::Json,
+            _ => ManifestFormat::Text,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ManifestError {
+    /// 1-based line number of the problem
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ManifestError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ManifestEntry {
+    /// Line the entry starts on
+    pub line: usize,
+
+    /// Module name.  Text manifests don't name their modules.
+    pub name: Option<String>,
+    pub mass: u32,
+
+    /// Number of identical modules described by the entry
+    pub count: u32,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Manifest {
+    pub entries: Vec<ManifestEntry>,
+}
+
+impl Manifest {
+    /// Expands the entries into one `Module` per counted module.
+    ///
+    /// Counts can be as large as `u32::MAX`, so the modules are produced lazily rather than
+    /// collected, and can be passed straight to `compute_fuel_requirement`.
+    pub fn modules(&self) -> impl Iterator<Item = Module> + '_ {
+        self.entries
+            .iter()
+            .flat_map(|e| std::iter::repeat_n(Module { mass: e.mass }, e.count as usize))
+    }
+}
+
+/// An entry as it appears in the manifest, before its fields are validated
+struct RawEntry {
+    line: usize,
+    name: Option<String>,
+    mass: String,
+    count: Option<String>,
+}
+
+fn error(errors: &mut Vec<ManifestError>, line: usize, message: String) {
+    errors.push(ManifestError { line, message });
+}
+
+fn parse_text(contents: &str, errors: &mut Vec<ManifestError>) -> Vec<RawEntry> {
+    let mut entries = Vec::new();
+    for (idx, line) in contents.lines().enumerate() {
+        let line_num = idx + 1;
+        let line = line.trim();
+        if line.is_empty() {
+            error(errors, line_num, "empty line".to_string());
+            continue;
+        }
+        entries.push(RawEntry {
+            line: line_num,
+            name: None,
+            mass: line.to_string(),
+            count: None,
+        });
+    }
+    entries
+}
+
+fn parse_csv(contents: &str, errors: &mut Vec<ManifestError>) -> Vec<RawEntry> {
+    let mut entries = Vec::new();
+    for (idx, line) in contents.lines().enumerate() {
+        let line_num = idx + 1;
+        let line = line.trim();
+        if line.is_empty() {
+            error(errors, line_num, "empty line".to_string());
+            continue;
+        }
+
+        let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+        if idx == 0 && fields.len() >= 2 && fields[0] == "name" && fields[1] == "mass" {
+            continue;
+        }
+        if fields.len() < 2 || fields.len() > 3 {
+            error(
+                errors,
+                line_num,
+                format!("expected 'name,mass' or 'name,mass,count', got '{}'", line),
+            );
+            continue;
+        }
+        entries.push(RawEntry {
+            line: line_num,
+            name: Some(fields[0].to_string()),
+            mass: fields[1].to_string(),
+            count: fields.get(2).map(|c| c.to_string()),
+        });
+    }
+    entries
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+enum Token {
+    Punct(char),
+    Str(String),
+
+    /// Anything else, e.g. a number, kept as written so that it can be validated later
+    Word(String),
+}
+
+/// Splits JSON into tokens, each paired with the line it starts on
+fn tokenize_json(contents: &str) -> Result<Vec<(usize, Token)>, ManifestError> {
+    let mut tokens = Vec::new();
+    let mut line = 1;
+    let mut chars = contents.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\n' => line += 1,
+            c if c.is_whitespace() => {}
+            '[' | ']' | '{' | '}' | ':' | ',' => tokens.push((line, Token::Punct(c))),
+            '"' => {
+                let start = line;
+                let mut s = String::new();
+                loop {
+                    match chars.next() {
+                        None => {
+                            return Err(ManifestError {
+                                line: start,
+                                message: "unterminated string".to_string(),
+                            })
+                        }
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(escaped @ ('"' | '\\' | '/')) => s.push(escaped),
+                            Some('n') => s.push('\n'),
+                            Some('t') => s.push('\t'),
+                            other => {
+                                return Err(ManifestError {
+                                    line,
+                                    message: format!("unsupported escape {:?}", other),
+                                })
+                            }
+                        },
+                        Some('\n') => {
+                            line += 1;
+                            s.push('\n');
+                        }
+                        Some(c) => s.push(c),
+                    }
+                }
+                tokens.push((start, Token::Str(s)));
+            }
+            c => {
+                let mut word = c.to_string();
+                while let Some(&next) = chars.peek() {
+                    if next.is_whitespace() || "[]{}:,\"".contains(next) {
+                        break;
+                    }
+                    word.push(next);
+                    chars.next();
+                }
+                tokens.push((line, Token::Word(word)));
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+/// Parses a JSON-like manifest.  Structural problems stop parsing, since nothing after them can
+/// be trusted.
+fn parse_json(contents: &str, errors: &mut Vec<ManifestError>) -> Vec<RawEntry> {
+    let tokens = match tokenize_json(contents) {
+        Ok(tokens) => tokens,
+        Err(e) => {
+            errors.push(e);
+            return Vec::new();
+        }
+    };
+    let last_line = contents.lines().count().max(1);
+    match parse_json_tokens(&tokens, last_line) {
+        Ok(entries) => entries,
+        Err(e) => {
+            errors.push(e);
+            Vec::new()
+        }
+    }
+}
+
+fn parse_json_tokens(
+    tokens: &[(usize, Token)],
+    last_line: usize,
+) -> Result<Vec<RawEntry>, ManifestError> {
+    let mut pos = 0;
+    let next = |pos: &mut usize, expected: &str| -> Result<(usize, Token), ManifestError> {
+        let token = tokens.get(*pos).cloned().ok_or_else(|| ManifestError {
+            line: last_line,
+            message: format!("expected {}, got end of input", expected),
+        })?;
+        *pos += 1;
+        Ok(token)
+    };
+    let unexpected = |(line, token): (usize, Token), expected: &str| ManifestError {
+        line,
+        message: format!("expected {}, got {:?}", expected, token),
+    };
+
+    let open = next(&mut pos, "'['")?;
+    if open.1 != Token::Punct('[') {
+        return Err(unexpected(open, "'['"));
+    }
+
+    let mut entries = Vec::new();
+    loop {
+        let (line, token) = next(&mut pos, "a mass, an object or ']'")?;
+        match token {
+            Token::Punct(']') if entries.is_empty() => break,
+            Token::Word(mass) => entries.push(RawEntry {
+                line,
+                name: None,
+                mass,
+                count: None,
+            }),
+            Token::Punct('{') => {
+                let mut name = None;
+                let mut mass = None;
+                let mut count = None;
+                let mut keys: Vec<String> = Vec::new();
+                loop {
+                    let key = match next(&mut pos, "a key or '}'")? {
+                        (_, Token::Punct('}')) if keys.is_empty() => break,
+                        (line, Token::Str(key)) => {
+                            if keys.contains(&key) {
+                                return Err(ManifestError {
+                                    line,
+                                    message: format!("duplicate key '{}'", key),
+                                });
+                            }
+                            keys.push(key.clone());
+                            key
+                        }
+                        other => return Err(unexpected(other, "a key")),
+                    };
+                    let colon = next(&mut pos, "':'")?;
+                    if colon.1 != Token::Punct(':') {
+                        return Err(unexpected(colon, "':'"));
+                    }
+                    let value = next(&mut pos, "a value")?;
+                    match (key.as_str(), value) {
+                        ("name", (_, Token::Str(s))) => name = Some(s),
+                        ("mass", (_, Token::Word(w))) => mass = Some(w),
+                        ("count", (_, Token::Word(w))) => count = Some(w),
+                        ("name", other) => return Err(unexpected(other, "a string")),
+                        ("mass", other) | ("count", other) => {
+                            return Err(unexpected(other, "a number"))
+                        }
+                        (_, (line, _)) => {
+                            return Err(ManifestError {
+                                line,
+                                message: format!("unknown key '{}'", key),
+                            })
+                        }
+                    }
+                    match next(&mut pos, "',' or '}'")? {
+                        (_, Token::Punct(',')) => continue,
+                        (_, Token::Punct('}')) => break,
+                        other => return Err(unexpected(other, "',' or '}'")),
+                    }
+                }
+                let mass = mass.ok_or_else(|| ManifestError {
+                    line,
+                    message: "module has no mass".to_string(),
+                })?;
+                entries.push(RawEntry {
+                    line,
+                    name,
+                    mass,
+                    count,
+                });
+            }
+            other => return Err(unexpected((line, other), "a mass or an object")),
+        }
+
+        match next(&mut pos, "',' or ']'")? {
+            (_, Token::Punct(',')) => continue,
+            (_, Token::Punct(']')) => break,
+            other => return Err(unexpected(other, "',' or ']'")),
+        }
+    }
+
+    if let Some(extra) = tokens.get(pos).cloned() {
+        return Err(unexpected(extra, "end of input"));
+    }
+    Ok(entries)
+}
+
+/// Parses a mass or count, distinguishing negative and overflowing values from other mistakes
+fn parse_quantity(what: &str, raw: &str) -> Result<u32, String> {
+    raw.parse().map_err(|e: std::num::ParseIntError| {
+        let is_integer = raw
+            .trim_start_matches('-')
+            .chars()
+            .all(|c| c.is_ascii_digit());
+        match e.kind() {
+            _ if raw.starts_with('-') && is_integer && raw.len() > 1 => {
+                format!("negative {} {}", what, raw)
+            }
+            IntErrorKind::PosOverflow => format!("{} {} is too large", what, raw),
+            _ => format!("invalid {} '{}'", what, raw),
+        }
+    })
+}
+
+fn validate(raw: Vec<RawEntry>, errors: &mut Vec<ManifestError>) -> Vec<ManifestEntry> {
+    let mut first_seen: HashMap<String, usize> = HashMap::new();
+    let mut entries = Vec::new();
+    for r in raw {
+        let mut valid = true;
+        if let Some(name) = &r.name {
+            if name.is_empty() {
+                error(errors, r.line, "missing module name".to_string());
+                valid = false;
+            } else if let Some(first) = first_seen.get(name) {
+                error(
+                    errors,
+                    r.line,
+                    format!(
+                        "duplicate module name '{}' (first used on line {})",
+                        name, first
+                    ),
+                );
+                valid = false;
+            } else {
+                first_seen.insert(name.clone(), r.line);
+            }
+        }
+
+        let mass = parse_quantity("mass", &r.mass);
+        let count = match &r.count {
+            None => Ok(1),
+            Some(c) => parse_quantity("count", c).and_then(|c| {
+                if c == 0 {
+                    Err("count must be at least 1".to_string())
+                } else {
+                    Ok(c)
+                }
+            }),
+        };
+        match (mass, count) {
+            (Ok(mass), Ok(count)) if valid => entries.push(ManifestEntry {
+                line: r.line,
+                name: r.name,
+                mass,
+                count,
+            }),
+            (mass, count) => {
+                for msg in mass.err().into_iter().chain(count.err()) {
+                    error(errors, r.line, msg);
+                }
+            }
+        }
+    }
+    entries
+}
+
+/// Parses and validates a manifest.  On failure, every problem found is returned, in line order.
+pub fn parse_manifest(
+    contents: &str,
+    format: ManifestFormat,
+) -> Result<Manifest, Vec<ManifestError>> {
+    let mut errors = Vec::new();
+    let raw = match format {
+        ManifestFormat::Text => parse_text(contents, &mut errors),
+        ManifestFormat::Csv => parse_csv(contents, &mut errors),
+        ManifestFormat::Json => parse_json(contents, &mut errors),
+    };
+    let entries = validate(raw, &mut errors);
+
+    if errors.is_empty() {
+        Ok(Manifest { entries })
+    } else {
+        errors.sort_by_key(|e| e.line);
+        Err(errors)
+    }
+}
+
+/// Reads a manifest from a file, choosing the format from its extension.  Errors are reported one
+/// per line, prefixed with the file name.
+pub fn load_manifest(path: &Path) -> Result<Manifest, String> {
+    let contents = fs::read_to_string(path)
+        .map_err(|e| format!("Unable to read {}: {}", path.display(), e))?;
+    parse_manifest(&contents, ManifestFormat::from_path(path)).map_err(|errors| {
+        errors
+            .iter()
+            .map(|e| format!("{}:{}: {}", path.display(), e.line, e.message))
+            .collect::<Vec<_>>()
+            .join("\n")
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::day1::{compute_fuel_requirement, get_modules, RocketEquation};
+
+    fn errors(contents: &str, format: ManifestFormat) -> Vec<String> {
+        parse_manifest(contents, format)
+            .unwrap_err()
+            .iter()
+            .map(ManifestError::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn test_text_manifest() {
+        let text: String = get_modules()
+            .iter()
+            .map(|m| format!("{}\n", m.mass))
+            .collect();
+        let manifest = parse_manifest(&text, ManifestFormat::Text).unwrap();
+        assert_eq!(get_modules(), manifest.modules().collect::<Vec<_>>());
+        assert_eq!(
+            compute_fuel_requirement(get_modules(), &RocketEquation),
+            compute_fuel_requirement(manifest.modules(), &RocketEquation)
+        );
+    }
+
+    #[test]
+    fn test_text_errors() {
+        assert_eq!(
+            vec![
+                "line 2: empty line",
+                "line 3: negative mass -5",
+                "line 4: mass 4294967296 is too large",
+                "line 5: invalid mass '1.5'",
+            ],
+            errors("12\n\n-5\n4294967296\n1.5\n14\n", ManifestFormat::Text)
+        );
+    }
+
+    #[test]
+    fn test_csv_manifest() {
+        let manifest = parse_manifest(
+            "name,mass,count\nprobe, 1969\nthruster,12,3\n",
+            ManifestFormat::Csv,
+        )
+        .unwrap();
+        assert_eq!(
+            vec![
+                ManifestEntry {
+                    line: 2,
+                    name: Some("probe".to_string()),
+                    mass: 1969,
+                    count: 1
+                },
+                ManifestEntry {
+                    line: 3,
+                    name: Some("thruster".to_string()),
+                    mass: 12,
+                    count: 3
+                },
+            ],
+            manifest.entries
+        );
+        assert_eq!(4, manifest.modules().count());
+    }
+
+    #[test]
+    fn test_large_counts() {
+        let manifest =
+            parse_manifest("a,12,4294967295\nb,14,4294967295\n", ManifestFormat::Csv).unwrap();
+        assert_eq!(
+            Some(Module { mass: 14 }),
+            manifest.modules().nth(u32::MAX as usize)
+        );
+    }
+
+    #[test]
+    fn test_csv_errors() {
+        assert_eq!(
+            vec![
+                "line 2: duplicate module name 'a' (first used on line 1)",
+                "line 3: expected 'name,mass' or 'name,mass,count', got 'b'",
+                "line 4: empty line",
+                "line 5: missing module name",
+                "line 6: count must be at least 1",
+                "line 7: negative mass -1",
+                "line 7: negative count -2",
+            ],
+            errors("a,1\na,2\nb\n\n,3\nc,4,0\nd,-1,-2\n", ManifestFormat::Csv)
+        );
+    }
+
+    #[test]
+    fn test_json_manifest() {
+        let manifest = parse_manifest(
+            "[\n  12,\n  {\"name\": \"probe\", \"mass\": 1969, \"count\": 2},\n  {\"mass\": 14}\n]",
+            ManifestFormat::Json,
+        )
+        .unwrap();
+        assert_eq!(
+            vec![
+                (2, None, 12, 1),
+                (3, Some("probe"), 1969, 2),
+                (4, None, 14, 1)
+            ],
+            manifest
+                .entries
+                .iter()
+                .map(|e| (e.line, e.name.as_deref(), e.mass, e.count))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            Ok(Manifest { entries: vec![] }),
+            parse_manifest("[]", ManifestFormat::Json)
+        );
+    }
+
+    #[test]
+    fn test_json_errors() {
+        assert_eq!(
+            vec![
+                "line 2: negative mass -12",
+                "line 3: duplicate module name 'a' (first used on line 1)",
+            ],
+            errors(
+                "[{\"name\": \"a\", \"mass\": 1},\n-12,\n{\"name\": \"a\", \"mass\": 2}]",
+                ManifestFormat::Json
+            )
+        );
+        assert_eq!(
+            vec!["line 2: expected ',' or ']', got Word(\"2\")"],
+            errors("[1\n2]", ManifestFormat::Json)
+        );
+        assert_eq!(
+            vec!["line 1: unknown key 'weight'"],
+            errors("[{\"weight\": 2}]", ManifestFormat::Json)
+        );
+        assert_eq!(
+            vec!["line 2: duplicate key 'mass'"],
+            errors("[{\"mass\": 1,\n\"mass\": 2}]", ManifestFormat::Json)
+        );
+        assert_eq!(
+            vec!["line 1: module has no mass"],
+            errors("[{\"name\": \"a\"}]", ManifestFormat::Json)
+        );
+        assert_eq!(
+            vec!["line 1: expected a mass, an object or ']', got end of input"],
+            errors("[1,\n", ManifestFormat::Json)
+        );
+        assert_eq!(
+            vec!["line 1: unterminated string"],
+            errors("[{\"name", ManifestFormat::Json)
+        );
+    }
+
+    #[test]
+    fn test_load_manifest() {
+        let path = std::env::temp_dir().join("aoc_day1_manifest.csv");
+        fs::write(&path, "a,12\nb,x\n").unwrap();
+        let err = load_manifest(&path).unwrap_err();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(format!("{}:2: invalid mass 'x'", path.display()), err);
+
+        assert!(load_manifest(Path::new("/nonexistent/manifest.txt"))
+            .unwrap_err()
+            .starts_with("Unable to read"));
+    }
+}
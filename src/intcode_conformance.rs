@@ -0,0 +1,292 @@
+/// Data-driven conformance suite for the Intcode executor.
+///
+/// Test cases are read from the text files in `conformance/intcode`.  Each case starts with a
+/// `[name]` header, followed by `key: value` lines:
+///
+/// * `program` - comma separated program (required)
+/// * `input` - comma separated input values
+/// * `output` - expected output values
+/// * `memory` - expected final contents of memory
+/// * `error` - text that the executor's error message must contain
+/// * `state` - expected final state, `halted` (the default) or `awaiting-input`
+///
+/// Blank lines and lines starting with `#` are ignored.
+use crate::day2::{IntCodeProgramExecutor, MemContent, ProgramState};
+use std::fs;
+use std::path::Path;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum ExpectedState {
+    Halted,
+    AwaitingInput,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+struct TestCase {
+    /// Source file and line number of the case header, for reporting
+    location: String,
+    name: String,
+    program: Vec<MemContent>,
+    input: Vec<MemContent>,
+    output: Option<Vec<MemContent>>,
+    memory: Option<Vec<MemContent>>,
+    error: Option<String>,
+    state: ExpectedState,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+struct CaseResult {
+    location: String,
+    name: String,
+
+    /// `None` if the case passed, otherwise a description of the failure
+    failure: Option<String>,
+}
+
+fn parse_values(s: &str) -> Result<Vec<MemContent>, String> {
+    s.split(',')
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+        .map(|v| v.parse().map_err(|_| format!("invalid value '{}'", v)))
+        .collect()
+}
+
+/// Parses the test cases in a single file.  `file` is only used for reporting.
+fn parse_cases(file: &str, contents: &str) -> Result<Vec<TestCase>, String> {
+    let mut cases: Vec<TestCase> = Vec::new();
+    let mut has_program = true;
+    for (idx, line) in contents.lines().enumerate() {
+        let line_num = idx + 1;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        if line.starts_with('[') && line.ends_with(']') {
+            if !has_program {
+                let prev = cases.last().unwrap();
+                return Err(format!(
+                    "{}: case '{}' has no program",
+                    prev.location, prev.name
+                ));
+            }
+            cases.push(TestCase {
+                location: format!("{}:{}", file, line_num),
+                name: line[1..line.len() - 1].trim().to_string(),
+                program: Vec::new(),
+                input: Vec::new(),
+                output: None,
+                memory: None,
+                error: None,
+                state: ExpectedState::Halted,
+            });
+            has_program = false;
+            continue;
+        }
+
+        let err = |reason: String| format!("{}:{}: {}", file, line_num, reason);
+        let case = cases
+            .last_mut()
+            .ok_or_else(|| err("expected a [name] header".to_string()))?;
+        let mut kv = line.splitn(2, ':');
+        let key = kv.next().unwrap().trim();
+        let value = kv
+            .next()
+            .ok_or_else(|| err(format!("expected 'key: value', got '{}'", line)))?
+            .trim();
+        match key {
+            "program" => {
+                case.program = parse_values(value).map_err(err)?;
+                has_program = true;
+            }
+            "input" => case.input = parse_values(value).map_err(err)?,
+            "output" => case.output = Some(parse_values(value).map_err(err)?),
+            "memory" => case.memory = Some(parse_values(value).map_err(err)?),
+            "error" => case.error = Some(value.to_string()),
+            "state" => {
+                case.state = match value {
+                    "halted" => ExpectedState::Halted,
+                    "awaiting-input" => ExpectedState::AwaitingInput,
+                    _ => return Err(err(format!("unknown state '{}'", value))),
+                }
+            }
+            _ => return Err(err(format!("unknown key '{}'", key))),
+        }
+    }
+
+    if !has_program {
+        let prev = cases.last().unwrap();
+        return Err(format!(
+            "{}: case '{}' has no program",
+            prev.location, prev.name
+        ));
+    }
+    Ok(cases)
+}
+
+/// Runs a single case, returning a description of the first mismatch
+fn run_case(case: &TestCase) -> Option<String> {
+    let mut memory = case.program.clone();
+    let mut exec = IntCodeProgramExecutor::from(&mut memory);
+    exec.mut_input().extend_from_slice(&case.input);
+    let result = exec.execute();
+    let output = exec.output().clone();
+
+    match (&case.error, result) {
+        (Some(expected), Ok(state)) => {
+            return Some(format!(
+                "expected error containing '{}', got {:?}",
+                expected, state
+            ))
+        }
+        (Some(expected), Err(msg)) => {
+            if !msg.contains(expected.as_str()) {
+                return Some(format!(
+                    "expected error containing '{}', got '{}'",
+                    expected, msg
+                ));
+            }
+        }
+        (None, Err(msg)) => return Some(format!("unexpected error '{}'", msg)),
+        (None, Ok(state)) => {
+            let matches = match state {
+                ProgramState::Terminated(_) => case.state == ExpectedState::Halted,
+                ProgramState::AwaitingInput => case.state == ExpectedState::AwaitingInput,
+            };
+            if !matches {
+                return Some(format!("expected {:?}, got {:?}", case.state, state));
+            }
+        }
+    }
+
+    if let Some(expected) = &case.output {
+        if *expected != output {
+            return Some(format!("expected output {:?}, got {:?}", expected, output));
+        }
+    }
+
+    if let Some(expected) = &case.memory {
+        if *expected != memory {
+            return Some(format!("expected memory {:?}, got {:?}", expected, memory));
+        }
+    }
+
+    None
+}
+
+/// Runs every case in every `.txt` file in the directory.  Files that can't be parsed are
+/// reported as a single failed case.
+fn run_suite(dir: &Path) -> Vec<CaseResult> {
+    let mut files: Vec<_> = fs::read_dir(dir)
+        .unwrap_or_else(|e| panic!("Unable to read {}: {}", dir.display(), e))
+        .map(|entry| entry.unwrap().path())
+        .filter(|p| p.extension().is_some_and(|ext| ext == "txt"))
+        .collect();
+    files.sort();
+
+    let mut results = Vec::new();
+    for path in files {
+        let file = path.file_name().unwrap().to_string_lossy().to_string();
+        let contents = fs::read_to_string(&path).unwrap();
+        match parse_cases(&file, &contents) {
+            Ok(cases) => results.extend(cases.iter().map(|case| CaseResult {
+                location: case.location.clone(),
+                name: case.name.clone(),
+                failure: run_case(case),
+            })),
+            Err(msg) => results.push(CaseResult {
+                location: file,
+                name: "<parse error>".to_string(),
+                failure: Some(msg),
+            }),
+        }
+    }
+    results
+}
+
+/// Formats the results as a table with one row per case
+fn format_table(results: &[CaseResult]) -> String {
+    let loc_width = results.iter().map(|r| r.location.len()).max().unwrap_or(0);
+    let name_width = results.iter().map(|r| r.name.len()).max().unwrap_or(0);
+    let mut table = String::new();
+    for r in results {
+        let status = if r.failure.is_some() { "FAIL" } else { "pass" };
+        table += &format!(
+            "{:<lw$}  {:<nw$}  {}",
+            r.location,
+            r.name,
+            status,
+            lw = loc_width,
+            nw = name_width
+        );
+        if let Some(msg) = &r.failure {
+            table += &format!("  {}", msg);
+        }
+        table += "\n";
+    }
+    let failed = results.iter().filter(|r| r.failure.is_some()).count();
+    table += &format!("{} passed, {} failed\n", results.len() - failed, failed);
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn conformance_suite() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("conformance/intcode");
+        let results = run_suite(&dir);
+        let table = format_table(&results);
+        println!("{}", table);
+        assert!(!results.is_empty());
+        assert!(results.iter().all(|r| r.failure.is_none()), "\n{}", table);
+    }
+
+    #[test]
+    fn test_parse_cases() {
+        let cases = parse_cases(
+            "t.txt",
+            "# comment\n[echo]\nprogram: 3,0,4,0,99\ninput: 5\noutput: 5\n",
+        )
+        .unwrap();
+        assert_eq!(1, cases.len());
+        assert_eq!("t.txt:2", cases[0].location);
+        assert_eq!("echo", cases[0].name);
+        assert_eq!(vec![3, 0, 4, 0, 99], cases[0].program);
+        assert_eq!(vec![5], cases[0].input);
+        assert_eq!(Some(vec![5]), cases[0].output);
+        assert_eq!(None, run_case(&cases[0]));
+    }
+
+    #[test]
+    fn test_parse_errors_report_line() {
+        assert_eq!(
+            Err("t.txt:3: invalid value 'x'".to_string()),
+            parse_cases("t.txt", "[a]\nprogram: 99\noutput: x\n")
+        );
+        assert_eq!(
+            Err("t.txt:1: case 'a' has no program".to_string()),
+            parse_cases("t.txt", "[a]\noutput: 1\n")
+        );
+        assert_eq!(
+            Err("t.txt:1: expected a [name] header".to_string()),
+            parse_cases("t.txt", "program: 99\n")
+        );
+    }
+
+    #[test]
+    fn test_failures_are_reported() {
+        let cases = parse_cases("t.txt", "[a]\nprogram: 104,1,99\noutput: 2\n").unwrap();
+        let failure = run_case(&cases[0]).unwrap();
+        assert_eq!("expected output [2], got [1]", failure);
+
+        let table = format_table(&[CaseResult {
+            location: "t.txt:1".to_string(),
+            name: "a".to_string(),
+            failure: Some(failure),
+        }]);
+        assert!(table.contains("FAIL"));
+        assert!(table.ends_with("0 passed, 1 failed\n"));
+    }
+}
@@ -0,0 +1,368 @@
+/// Differential fuzz harness for the Intcode interpreter.
+///
+/// Random well-formed programs are run through both `IntCodeProgramExecutor` and a small,
+/// independently written reference interpreter.  The two must agree on the outputs produced, the
+/// final contents of memory, and the kind of error (if any) that stopped execution.
+use crate::day2::{IntCodeProgramExecutor, MemContent, ProgramState};
+use proptest::prelude::*;
+use std::collections::HashMap;
+
+/// Maximum number of instructions the reference interpreter executes before giving up
+const STEP_LIMIT: usize = 1000;
+
+/// Highest address the reference interpreter will write to before giving up.  This keeps runaway
+/// programs from allocating huge amounts of memory in the executor.
+const ADDR_LIMIT: i64 = 4096;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum ErrorKind {
+    UnknownOpcode,
+    UnknownParameterMode,
+    NegativeAddress,
+    ImmediateModeWrite,
+    Overflow,
+}
+
+impl ErrorKind {
+    /// Classifies an executor error message
+    pub fn classify(msg: &str) -> Option<ErrorKind> {
+        if msg.starts_with("Unexpected opcode") {
+            Some(ErrorKind::UnknownOpcode)
+        } else if msg.contains("parameter mode") {
+            Some(ErrorKind::UnknownParameterMode)
+        } else if msg.starts_with("Negative address") {
+            Some(ErrorKind::NegativeAddress)
+        } else if msg.starts_with("Immediate mode write") {
+            Some(ErrorKind::ImmediateModeWrite)
+        } else if msg.starts_with("Arithmetic overflow") {
+            Some(ErrorKind::Overflow)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Outcome {
+    Halted,
+    AwaitingInput,
+    Error(ErrorKind),
+
+    /// The reference interpreter hit `STEP_LIMIT` or `ADDR_LIMIT`.  The executor is not run.
+    Inconclusive,
+}
+
+/// Everything observable about a single program run
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Run {
+    pub outcome: Outcome,
+    pub output: Vec<MemContent>,
+    pub memory: Vec<MemContent>,
+}
+
+/// Reference interpreter.  Memory is sparse, and instructions are decoded up front into an opcode
+/// and a list of parameter modes, rather than being parsed digit by digit as they're used.
+struct Reference {
+    mem: HashMap<i64, MemContent>,
+    len: i64,
+    ip: i64,
+    base: i64,
+}
+
+/// Reference interpreter steps return the outcome that stopped execution as their error
+type Step<T> = std::result::Result<T, Outcome>;
+
+fn fail<T>(kind: ErrorKind) -> Step<T> {
+    Err(Outcome::Error(kind))
+}
+
+/// Adds an offset to an address, failing on overflow or a negative result
+fn offset(base: i64, offset: i64) -> Step<i64> {
+    match base.checked_add(offset) {
+        None => fail(ErrorKind::Overflow),
+        Some(addr) if addr < 0 => fail(ErrorKind::NegativeAddress),
+        Some(addr) => Ok(addr),
+    }
+}
+
+impl Reference {
+    fn read(&self, addr: i64) -> Step<MemContent> {
+        if addr < 0 {
+            return fail(ErrorKind::NegativeAddress);
+        }
+        Ok(*self.mem.get(&addr).unwrap_or(&0))
+    }
+
+    fn write(&mut self, addr: i64, value: MemContent) -> Step<()> {
+        if addr > ADDR_LIMIT {
+            return Err(Outcome::Inconclusive);
+        }
+        self.mem.insert(addr, value);
+        self.len = self.len.max(addr + 1);
+        Ok(())
+    }
+
+    /// Splits the instruction at `ip` into its opcode and the modes of up to three parameters
+    fn decode(&self) -> Step<(i64, [i64; 3])> {
+        let instr = self.read(self.ip)?;
+        let opcode = instr % 100;
+        let modes = [instr / 100 % 10, instr / 1000 % 10, instr / 10000 % 10];
+        Ok((opcode, modes))
+    }
+
+    fn check_modes(modes: &[i64]) -> Step<()> {
+        if modes.iter().all(|m| (0..=2).contains(m)) {
+            Ok(())
+        } else {
+            fail(ErrorKind::UnknownParameterMode)
+        }
+    }
+
+    fn load(&self, n: usize, mode: i64) -> Step<MemContent> {
+        Reference::check_modes(&[mode])?;
+        let raw = self.read(self.ip + n as i64)?;
+        match mode {
+            0 => self.read(raw),
+            1 => Ok(raw),
+            _ => self.read(offset(self.base, raw)?),
+        }
+    }
+
+    fn store_addr(&self, n: usize, mode: i64) -> Step<i64> {
+        Reference::check_modes(&[mode])?;
+        let raw = self.read(self.ip + n as i64)?;
+        match mode {
+            0 => offset(0, raw),
+            1 => fail(ErrorKind::ImmediateModeWrite),
+            _ => offset(self.base, raw),
+        }
+    }
+
+    fn jump_target(value: MemContent) -> Step<i64> {
+        if value < 0 {
+            fail(ErrorKind::NegativeAddress)
+        } else {
+            Ok(value)
+        }
+    }
+
+    fn step(&mut self, input: &mut Vec<MemContent>, output: &mut Vec<MemContent>) -> Step<bool> {
+        let (opcode, m) = self.decode()?;
+        match opcode {
+            1 | 2 | 7 | 8 => {
+                let a = self.load(1, m[0])?;
+                let b = self.load(2, m[1])?;
+                let dest = self.store_addr(3, m[2])?;
+                let value = match opcode {
+                    1 => a.checked_add(b),
+                    2 => a.checked_mul(b),
+                    7 => Some((a < b) as MemContent),
+                    _ => Some((a == b) as MemContent),
+                };
+                match value {
+                    Some(v) => self.write(dest, v)?,
+                    None => return fail(ErrorKind::Overflow),
+                }
+                self.ip += 4;
+            }
+            3 => {
+                let dest = self.store_addr(1, m[0])?;
+                if input.is_empty() {
+                    return Err(Outcome::AwaitingInput);
+                }
+                let value = input.remove(0);
+                self.write(dest, value)?;
+                self.ip += 2;
+            }
+            4 => {
+                output.push(self.load(1, m[0])?);
+                self.ip += 2;
+            }
+            5 | 6 => {
+                let cond = self.load(1, m[0])?;
+                let target = self.load(2, m[1])?;
+                if (cond != 0) == (opcode == 5) {
+                    self.ip = Reference::jump_target(target)?;
+                } else {
+                    self.ip += 3;
+                }
+            }
+            9 => {
+                let adj = self.load(1, m[0])?;
+                self.base = offset(self.base, adj)?;
+                self.ip += 2;
+            }
+            99 => return Ok(false),
+            _ => return fail(ErrorKind::UnknownOpcode),
+        }
+        Ok(true)
+    }
+}
+
+/// Runs the program on the reference interpreter
+pub fn reference_run(program: &[MemContent], input: &[MemContent]) -> Run {
+    let mut r = Reference {
+        mem: (0..).zip(program.iter().cloned()).collect(),
+        len: program.len() as i64,
+        ip: 0,
+        base: 0,
+    };
+    let mut input = input.to_vec();
+    let mut output = Vec::new();
+
+    let mut outcome = Outcome::Inconclusive;
+    for _ in 0..STEP_LIMIT {
+        match r.step(&mut input, &mut output) {
+            Ok(true) => continue,
+            Ok(false) => outcome = Outcome::Halted,
+            Err(o) => outcome = o,
+        }
+        break;
+    }
+
+    let memory = (0..r.len).map(|a| *r.mem.get(&a).unwrap_or(&0)).collect();
+    Run {
+        outcome,
+        output,
+        memory,
+    }
+}
+
+/// Runs the program on `IntCodeProgramExecutor`
+pub fn executor_run(program: &[MemContent], input: &[MemContent]) -> Run {
+    let mut memory = program.to_vec();
+    let mut exec = IntCodeProgramExecutor::from(&mut memory);
+    exec.mut_input().extend_from_slice(input);
+    let outcome = match exec.execute() {
+        Ok(ProgramState::Terminated(_)) => Outcome::Halted,
+        Ok(ProgramState::AwaitingInput) => Outcome::AwaitingInput,
+        Err(msg) => Outcome::Error(
+            ErrorKind::classify(&msg).unwrap_or_else(|| panic!("Unclassified error: {}", msg)),
+        ),
+    };
+    let output = exec.output().clone();
+    Run {
+        outcome,
+        output,
+        memory,
+    }
+}
+
+/// Runs the program through both interpreters, and fails if they disagree.  Runs that the
+/// reference interpreter can't finish within its limits are skipped.
+pub fn check_agreement(
+    program: &[MemContent],
+    input: &[MemContent],
+) -> std::result::Result<(), TestCaseError> {
+    let expected = reference_run(program, input);
+    if expected.outcome == Outcome::Inconclusive {
+        return Ok(());
+    }
+    let actual = executor_run(program, input);
+    prop_assert_eq!(expected, actual);
+    Ok(())
+}
+
+/// Generates a single instruction: a (mostly) valid opcode, parameter modes, and small
+/// parameters that favour addresses near the start of memory, including negative offsets.
+fn arb_instruction() -> impl Strategy<Value = Vec<MemContent>> {
+    let opcode = prop_oneof![
+        20 => prop::sample::select(vec![1, 2, 3, 4, 5, 6, 7, 8, 9, 99]),
+        1 => prop::sample::select(vec![0, 10, 98]),
+    ];
+    let mode = prop_oneof![10 => 0..=2_i64, 1 => Just(3_i64)];
+    let param = prop_oneof![4 => 0..40_i64, 2 => -5..5_i64, 1 => -100..100_i64];
+    (
+        opcode,
+        prop::collection::vec(mode, 3),
+        prop::collection::vec(param, 3),
+    )
+        .prop_map(|(opcode, modes, params)| {
+            let n_params = match opcode {
+                1 | 2 | 7 | 8 => 3,
+                5 | 6 => 2,
+                3 | 4 | 9 => 1,
+                _ => 0,
+            };
+            let mut instr = opcode + 100 * modes[0] + 1000 * modes[1] + 10000 * modes[2];
+            if n_params == 0 {
+                instr = opcode;
+            }
+            let mut cells = vec![instr];
+            cells.extend_from_slice(&params[..n_params]);
+            cells
+        })
+}
+
+/// Generates a program: a sequence of instructions, followed by some data
+pub fn arb_program() -> impl Strategy<Value = Vec<MemContent>> {
+    (
+        prop::collection::vec(arb_instruction(), 1..12),
+        prop::collection::vec(-20..50_i64, 0..8),
+    )
+        .prop_map(|(instrs, data)| {
+            let mut program: Vec<MemContent> = instrs.into_iter().flatten().collect();
+            program.extend(data);
+            program
+        })
+}
+
+pub fn arb_input() -> impl Strategy<Value = Vec<MemContent>> {
+    prop::collection::vec(-50..50_i64, 0..4)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(2000))]
+
+        #[test]
+        fn executor_agrees_with_reference(program in arb_program(), input in arb_input()) {
+            check_agreement(&program, &input)?;
+        }
+    }
+
+    #[test]
+    fn test_read_at_len_is_zero() {
+        // outputs the cell just past the end of the program
+        let program = vec![4, 3, 99];
+        assert_eq!(executor_run(&program, &[]).output, vec![0]);
+        check_agreement(&program, &[]).unwrap();
+    }
+
+    #[test]
+    fn test_negative_relative_offset() {
+        let program = vec![109, 4, 204, -3, 99];
+        assert_eq!(executor_run(&program, &[]).output, vec![4]);
+        check_agreement(&program, &[]).unwrap();
+
+        let program = vec![204, -1, 99];
+        assert_eq!(
+            executor_run(&program, &[]).outcome,
+            Outcome::Error(ErrorKind::NegativeAddress)
+        );
+        check_agreement(&program, &[]).unwrap();
+    }
+
+    #[test]
+    fn test_write_past_end_grows_memory() {
+        let program = vec![1101, 2, 3, 6, 99];
+        assert_eq!(
+            executor_run(&program, &[]).memory,
+            vec![1101, 2, 3, 6, 99, 0, 5]
+        );
+        check_agreement(&program, &[]).unwrap();
+    }
+
+    #[test]
+    fn test_immediate_mode_write() {
+        let program = vec![11101, 2, 3, 0, 99];
+        assert_eq!(
+            executor_run(&program, &[]).outcome,
+            Outcome::Error(ErrorKind::ImmediateModeWrite)
+        );
+        check_agreement(&program, &[]).unwrap();
+    }
+}
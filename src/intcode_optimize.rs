@@ -0,0 +1,486 @@
+/// Peephole optimizer for Intcode programs.
+///
+/// The optimizer only rewrites programs whose control flow and memory accesses can be determined
+/// statically: every jump target must be immediate, and there must be no relative mode
+/// parameters.  Programs that write into (or read from) their own code are refused, since
+/// rewriting the code would change what they see.
+///
+/// The following rewrites are applied, in order:
+///
+/// 1. Arithmetic and comparisons with constant operands are folded into a constant store
+///    (`add #c #0 -> x`).  Operands are constant if they're immediate, or read from a cell that's
+///    never written.  Outputs of constants are made immediate, and conditional jumps on a
+///    constant are made unconditional, or into a jump to the next instruction.
+/// 2. Jumps to unconditional jumps are retargeted to the final destination.
+/// 3. Unreachable code, unreferenced data and jumps to the next instruction are dropped, and the
+///    remaining addresses are relocated.
+///
+/// The passes are repeated until the program stops changing.
+use crate::day2::{decode_instruction, Addr, MemContent, OpCode, ParameterMode, Result};
+use std::collections::{BTreeMap, BTreeSet};
+use std::convert::TryFrom;
+
+/// A decoded, reachable instruction
+#[derive(Debug, Clone)]
+struct Instr {
+    opcode: OpCode,
+    modes: Vec<ParameterMode>,
+}
+
+impl Instr {
+    fn len(&self) -> usize {
+        self.modes.len() + 1
+    }
+
+    /// Whether this is a jump that's always taken
+    fn is_unconditional_jump(&self, prog: &[MemContent], addr: Addr) -> bool {
+        let taken_if_nonzero = match self.opcode {
+            OpCode::JumpIfTrue => true,
+            OpCode::JumpIfFalse => false,
+            _ => return false,
+        };
+        self.modes[0] == ParameterMode::ImmediateMode && (prog[addr + 1] != 0) == taken_if_nonzero
+    }
+}
+
+/// Everything the optimizer knows about a program
+struct Analysis {
+    /// Reachable instructions, keyed by address
+    instrs: BTreeMap<Addr, Instr>,
+
+    /// Addresses occupied by reachable instructions and their parameters
+    code: BTreeSet<Addr>,
+
+    /// Addresses read by position mode parameters
+    read: BTreeSet<Addr>,
+
+    /// Addresses written by position mode parameters
+    written: BTreeSet<Addr>,
+}
+
+fn to_addr(value: MemContent, instr_addr: Addr) -> Result<Addr> {
+    usize::try_from(value).map_err(|_| format!("negative address at {}", instr_addr))
+}
+
+fn analyze(prog: &[MemContent]) -> Result<Analysis> {
+    let mut analysis = Analysis {
+        instrs: BTreeMap::new(),
+        code: BTreeSet::new(),
+        read: BTreeSet::new(),
+        written: BTreeSet::new(),
+    };
+
+    let mut pending = vec![0];
+    while let Some(addr) = pending.pop() {
+        if analysis.instrs.contains_key(&addr) {
+            continue;
+        }
+
+        let (opcode, modes) =
+            decode_instruction(prog, addr).map_err(|e| format!("at {}: {}", addr, e))?;
+        let instr = Instr { opcode, modes };
+        if addr + instr.len() > prog.len() {
+            return Err(format!("instruction at {} runs past the end", addr));
+        }
+
+        for (idx, mode) in instr.modes.iter().enumerate() {
+            let param = prog[addr + idx + 1];
+            let is_write = opcode.write_param() == Some(idx + 1);
+            match mode {
+                ParameterMode::RelativeMode => {
+                    return Err(format!("relative addressing at {}", addr));
+                }
+                ParameterMode::ImmediateMode if is_write => {
+                    return Err(format!("immediate mode write at {}", addr));
+                }
+                ParameterMode::ImmediateMode => {}
+                ParameterMode::PositionMode if is_write => {
+                    analysis.written.insert(to_addr(param, addr)?);
+                }
+                ParameterMode::PositionMode => {
+                    analysis.read.insert(to_addr(param, addr)?);
+                }
+            }
+        }
+
+        match opcode {
+            OpCode::Halt => {}
+            OpCode::JumpIfTrue | OpCode::JumpIfFalse => {
+                if instr.modes[1] != ParameterMode::ImmediateMode {
+                    return Err(format!("indirect jump at {}", addr));
+                }
+                pending.push(to_addr(prog[addr + 2], addr)?);
+                if !instr.is_unconditional_jump(prog, addr) {
+                    pending.push(addr + 3);
+                }
+            }
+            _ => pending.push(addr + instr.len()),
+        }
+
+        analysis.code.extend(addr..addr + instr.len());
+        analysis.instrs.insert(addr, instr);
+    }
+
+    if let Some(addr) = analysis.written.intersection(&analysis.code).next() {
+        return Err(format!("program writes into its own code at {}", addr));
+    }
+    if let Some(addr) = analysis.read.intersection(&analysis.code).next() {
+        return Err(format!("program reads its own code at {}", addr));
+    }
+    Ok(analysis)
+}
+
+impl Analysis {
+    /// Returns the value of the parameter if it's known before the program runs
+    fn constant(&self, prog: &[MemContent], addr: Addr, idx: usize) -> Option<MemContent> {
+        let param = prog[addr + idx + 1];
+        match self.instrs[&addr].modes[idx] {
+            ParameterMode::ImmediateMode => Some(param),
+            ParameterMode::PositionMode => {
+                let loc = param as Addr;
+                if self.written.contains(&loc) {
+                    None
+                } else {
+                    Some(prog.get(loc).cloned().unwrap_or_default())
+                }
+            }
+            ParameterMode::RelativeMode => None,
+        }
+    }
+}
+
+/// Folds instructions with constant operands.  Returns whether anything changed.
+fn fold_constants(prog: &mut [MemContent], analysis: &Analysis) -> bool {
+    let mut changed = false;
+    for (&addr, instr) in &analysis.instrs {
+        let operand = |idx: usize| {
+            if idx < instr.modes.len() {
+                analysis.constant(prog, addr, idx)
+            } else {
+                None
+            }
+        };
+        let a = operand(0);
+        let b = operand(1);
+        let replacement = match (instr.opcode, a, b) {
+            (OpCode::Add, Some(a), Some(b)) => a.checked_add(b),
+            (OpCode::Multiply, Some(a), Some(b)) => a.checked_mul(b),
+            (OpCode::LessThan, Some(a), Some(b)) => Some(if a < b { 1 } else { 0 }),
+            (OpCode::Equals, Some(a), Some(b)) => Some(if a == b { 1 } else { 0 }),
+            _ => None,
+        }
+        .map(|c| vec![1101, c, 0, prog[addr + 3]])
+        .or_else(|| match (instr.opcode, a) {
+            (OpCode::JumpIfTrue, Some(cond)) | (OpCode::JumpIfFalse, Some(cond)) => {
+                let taken = (cond != 0) == (instr.opcode == OpCode::JumpIfTrue);
+                let target = if taken {
+                    prog[addr + 2]
+                } else {
+                    (addr + 3) as MemContent
+                };
+                Some(vec![1105, 1, target])
+            }
+            (OpCode::Output, Some(value)) => Some(vec![104, value]),
+            _ => None,
+        });
+
+        if let Some(replacement) = replacement {
+            if prog[addr..addr + replacement.len()] != replacement[..] {
+                prog[addr..addr + replacement.len()].copy_from_slice(&replacement);
+                changed = true;
+            }
+        }
+    }
+    changed
+}
+
+/// Retargets jumps that land on unconditional jumps.  Returns whether anything changed.
+fn thread_jumps(prog: &mut [MemContent], analysis: &Analysis) -> bool {
+    let mut changed = false;
+    for (&addr, instr) in &analysis.instrs {
+        if instr.opcode != OpCode::JumpIfTrue && instr.opcode != OpCode::JumpIfFalse {
+            continue;
+        }
+
+        let mut target = prog[addr + 2] as Addr;
+        let mut seen = BTreeSet::new();
+        while let Some(next) = analysis.instrs.get(&target) {
+            if !next.is_unconditional_jump(prog, target) || !seen.insert(target) {
+                break;
+            }
+            target = prog[target + 2] as Addr;
+        }
+
+        if prog[addr + 2] != target as MemContent {
+            prog[addr + 2] = target as MemContent;
+            changed = true;
+        }
+    }
+    changed
+}
+
+/// Drops cells that are neither reachable code nor referenced data, along with jumps to the next
+/// instruction, and relocates every address in the program to match.
+fn compact(prog: &[MemContent], analysis: &Analysis) -> Vec<MemContent> {
+    let mut keep: Vec<bool> = (0..prog.len())
+        .map(|addr| {
+            analysis.code.contains(&addr)
+                || analysis.read.contains(&addr)
+                || analysis.written.contains(&addr)
+        })
+        .collect();
+    for (&addr, instr) in &analysis.instrs {
+        let is_jump = instr.opcode == OpCode::JumpIfTrue || instr.opcode == OpCode::JumpIfFalse;
+        if is_jump && prog[addr + 2] == (addr + 3) as MemContent {
+            keep[addr..addr + 3].iter_mut().for_each(|k| *k = false);
+        }
+    }
+
+    // new_addr[a] is the number of kept cells before a
+    let mut new_addr = Vec::with_capacity(prog.len() + 1);
+    let mut kept = 0;
+    for &k in &keep {
+        new_addr.push(kept);
+        kept += k as usize;
+    }
+    let removed = prog.len() - kept;
+    let relocate = |addr: MemContent| -> MemContent {
+        let addr = addr as Addr;
+        let relocated = new_addr
+            .get(addr)
+            .cloned()
+            .unwrap_or_else(|| addr - removed);
+        relocated as MemContent
+    };
+
+    let mut out: Vec<MemContent> = (0..prog.len())
+        .filter(|&addr| keep[addr])
+        .map(|addr| prog[addr])
+        .collect();
+    for (&addr, instr) in &analysis.instrs {
+        if !keep[addr] {
+            continue;
+        }
+        let is_jump = instr.opcode == OpCode::JumpIfTrue || instr.opcode == OpCode::JumpIfFalse;
+        for (idx, mode) in instr.modes.iter().enumerate() {
+            let is_target = is_jump && idx == 1;
+            if *mode == ParameterMode::PositionMode || is_target {
+                let cell = addr + idx + 1;
+                out[new_addr[cell]] = relocate(prog[cell]);
+            }
+        }
+    }
+    out
+}
+
+/// Optimizes the program, or explains why it can't be optimized safely
+pub fn optimize(program: &[MemContent]) -> Result<Vec<MemContent>> {
+    let mut prog = program.to_vec();
+    loop {
+        let next = optimize_pass(&prog)?;
+        if next == prog {
+            return Ok(next);
+        }
+        prog = next;
+    }
+}
+
+fn optimize_pass(program: &[MemContent]) -> Result<Vec<MemContent>> {
+    let mut prog = program.to_vec();
+
+    let mut analysis = analyze(&prog)?;
+    while fold_constants(&mut prog, &analysis) {
+        analysis = analyze(&prog)?;
+    }
+
+    if thread_jumps(&mut prog, &analysis) {
+        analysis = analyze(&prog)?;
+    }
+
+    Ok(compact(&prog, &analysis))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode_fuzz::{arb_input, arb_program, executor_run, reference_run, Outcome};
+    use proptest::prelude::*;
+
+    /// Asserts that both programs produce the same output on the executor
+    fn assert_same_output(orig: &[MemContent], opt: &[MemContent], input: &[MemContent]) {
+        let expected = executor_run(orig, input);
+        let actual = executor_run(opt, input);
+        assert_eq!(expected.outcome, actual.outcome);
+        assert_eq!(expected.output, actual.output);
+    }
+
+    #[test]
+    fn test_fold_add() {
+        // x = 2 + 3; input -> y; output x
+        let prog = vec![1101, 2, 3, 9, 3, 10, 4, 9, 99, 0, 0];
+        let opt = optimize(&prog).unwrap();
+        assert_eq!(vec![1101, 5, 0, 9, 3, 10, 4, 9, 99, 0, 0], opt);
+        assert_same_output(&prog, &opt, &[]);
+    }
+
+    #[test]
+    fn test_fold_reads_of_constant_data() {
+        // output data[0] * data[1]; the data cells are never written, so they fold away
+        let prog = vec![2, 9, 10, 11, 4, 11, 99, 42, 42, 6, 7, 0];
+        let opt = optimize(&prog).unwrap();
+        assert_eq!(vec![1101, 42, 0, 7, 4, 7, 99, 0], opt);
+        assert_same_output(&prog, &opt, &[]);
+    }
+
+    #[test]
+    fn test_no_fold_when_operand_is_written() {
+        // input -> x; output (x + 1)
+        let prog = vec![3, 9, 1001, 9, 1, 10, 4, 10, 99, 0, 0];
+        let opt = optimize(&prog).unwrap();
+        assert_eq!(prog, opt);
+        assert_same_output(&prog, &opt, &[41]);
+    }
+
+    #[test]
+    fn test_thread_jumps() {
+        // input -> x; if x jump to 9, which jumps to 12: output 1.  Otherwise output 0.
+        let prog = vec![
+            3, 17, 1005, 17, 9, 104, 0, 99, 0, 1105, 1, 12, 104, 1, 99, 0, 0, 0,
+        ];
+        let opt = optimize(&prog).unwrap();
+        assert_eq!(vec![3, 11, 1005, 11, 8, 104, 0, 99, 104, 1, 99, 0], opt);
+        assert_same_output(&prog, &opt, &[0]);
+        assert_same_output(&prog, &opt, &[5]);
+    }
+
+    #[test]
+    fn test_drop_unreachable_code() {
+        // jump over a dead output instruction, then halt
+        let prog = vec![1105, 1, 5, 104, 7, 104, 3, 99];
+        let opt = optimize(&prog).unwrap();
+        assert_eq!(vec![104, 3, 99], opt);
+        assert_same_output(&prog, &opt, &[]);
+    }
+
+    #[test]
+    fn test_drop_constant_branch() {
+        // jump-if-false on a constant 1 is never taken, so the jump disappears entirely
+        let prog = vec![1106, 1, 5, 104, 8, 99];
+        let opt = optimize(&prog).unwrap();
+        assert_eq!(vec![104, 8, 99], opt);
+        assert_same_output(&prog, &opt, &[]);
+    }
+
+    #[test]
+    fn test_relocates_writes_past_end() {
+        let prog = vec![1105, 1, 4, 0, 3, 20, 4, 20, 99];
+        let opt = optimize(&prog).unwrap();
+        assert_eq!(vec![3, 16, 4, 16, 99], opt);
+        assert_same_output(&prog, &opt, &[6]);
+    }
+
+    #[test]
+    fn test_refuses_self_modifying_code() {
+        let err = optimize(&[1, 0, 0, 0, 99]).unwrap_err();
+        assert!(err.contains("writes into its own code"), "{}", err);
+
+        let err = optimize(&crate::day2::get_gravity_assist_program()).unwrap_err();
+        assert!(err.contains("writes into its own code"), "{}", err);
+    }
+
+    #[test]
+    fn test_refuses_unanalyzable_programs() {
+        assert!(optimize(&[109, 1, 204, -1, 99]).is_err());
+        assert!(optimize(&[1106, 0, 3, 0]).is_err());
+        assert!(optimize(&[3, 4, 1005, 4, 4, 99]).is_err());
+    }
+
+    /// Generates programs the optimizer can analyze: no relative addressing, immediate jump
+    /// targets, and position mode parameters that only refer to the data following the code.
+    fn arb_analyzable_program() -> impl Strategy<Value = Vec<MemContent>> {
+        let instr = (
+            prop::sample::select(vec![1, 2, 3, 4, 5, 6, 7, 8, 99]),
+            prop::collection::vec(0..=1_i64, 2),
+            prop::collection::vec(-3..6_i64, 3),
+        );
+        (
+            prop::collection::vec(instr, 1..16),
+            prop::collection::vec(-3..6_i64, 6),
+        )
+            .prop_map(|(instrs, data)| {
+                let lengths: Vec<usize> = instrs
+                    .iter()
+                    .map(|(op, _, _)| OpCode::try_from(*op).unwrap().num_params() + 1)
+                    .collect();
+                let starts: Vec<MemContent> = lengths
+                    .iter()
+                    .scan(0, |addr, len| {
+                        *addr += len;
+                        Some((*addr - len) as MemContent)
+                    })
+                    .collect();
+                let data_start = lengths.iter().sum::<usize>() as MemContent;
+                let data_addr = |p: MemContent| data_start + p.rem_euclid(data.len() as MemContent);
+
+                let mut prog = Vec::new();
+                for (opcode, modes, params) in instrs {
+                    let opcode_enum = OpCode::try_from(opcode).unwrap();
+                    let mut instr = opcode;
+                    let mut cells = Vec::new();
+                    for idx in 0..opcode_enum.num_params() {
+                        let is_write = opcode_enum.write_param() == Some(idx + 1);
+                        let is_target = opcode == 5 || opcode == 6;
+                        let cell = if is_target && idx == 1 {
+                            instr += 1000;
+                            starts[params[idx].rem_euclid(starts.len() as MemContent) as usize]
+                        } else if modes[idx.min(1)] == 1 && !is_write {
+                            instr += 100 * 10_i64.pow(idx as u32);
+                            params[idx]
+                        } else {
+                            data_addr(params[idx])
+                        };
+                        cells.push(cell);
+                    }
+                    prog.push(instr);
+                    prog.extend(cells);
+                }
+                prog.extend(data);
+                prog
+            })
+    }
+
+    fn check_optimized(
+        program: &[MemContent],
+        input: &[MemContent],
+    ) -> std::result::Result<(), TestCaseError> {
+        let opt = match optimize(program) {
+            Ok(opt) => opt,
+            Err(_) => return Ok(()),
+        };
+        let expected = reference_run(program, input);
+        if expected.outcome == Outcome::Inconclusive {
+            return Ok(());
+        }
+        let actual = reference_run(&opt, input);
+        prop_assert_eq!(expected.outcome, actual.outcome);
+        prop_assert_eq!(expected.output, actual.output);
+        crate::intcode_fuzz::check_agreement(&opt, input)
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(2000))]
+
+        #[test]
+        fn optimized_programs_are_equivalent(program in arb_program(), input in arb_input()) {
+            check_optimized(&program, &input)?;
+        }
+
+        #[test]
+        fn optimized_analyzable_programs_are_equivalent(
+            program in arb_analyzable_program(),
+            input in arb_input(),
+        ) {
+            check_optimized(&program, &input)?;
+        }
+    }
+}
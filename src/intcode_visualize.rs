@@ -0,0 +1,313 @@
+/// Renders Intcode memory traces as heat map images.
+///
+/// Addresses run along the horizontal axis and time (executed instructions) down the vertical
+/// axis.  Each pixel's red, green and blue channels show how often the cells it covers were
+/// written, read and executed, on a log scale.  Long traces and large memories are downsampled
+/// to fit within the configured image size.
+///
+/// Images are encoded as binary PPM or as PNG without any external dependencies, so they can be
+/// produced on a headless machine and viewed elsewhere.
+use crate::day2::{AccessKind, IntCodeProgramExecutor, MemContent, MemoryAccess, Result};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct HeatMapOptions {
+    /// Maximum image width, in pixels.  Adjacent addresses are merged to fit.
+    pub max_width: usize,
+
+    /// Maximum image height, in pixels.  Consecutive steps are merged to fit.
+    pub max_height: usize,
+}
+
+impl Default for HeatMapOptions {
+    fn default() -> Self {
+        HeatMapOptions {
+            max_width: 1024,
+            max_height: 1024,
+        }
+    }
+}
+
+/// An RGB image, stored row by row
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<[u8; 3]>,
+}
+
+/// Runs the program with the given input, and returns the memory accesses it made
+pub fn trace_program(program: &[MemContent], input: &[MemContent]) -> Result<Vec<MemoryAccess>> {
+    let mut prog = program.to_vec();
+    let mut exec = IntCodeProgramExecutor::from(&mut prog);
+    exec.enable_trace();
+    exec.mut_input().extend_from_slice(input);
+    exec.execute()?;
+    Ok(exec.trace().unwrap().clone())
+}
+
+pub fn render_heat_map(trace: &[MemoryAccess], options: &HeatMapOptions) -> Image {
+    let num_addrs = trace.iter().map(|a| a.addr + 1).max().unwrap_or(1);
+    let num_steps = trace.iter().map(|a| a.step + 1).max().unwrap_or(1);
+    let addrs_per_col = num_addrs.div_ceil(options.max_width.max(1));
+    let steps_per_row = num_steps.div_ceil(options.max_height.max(1));
+    let width = num_addrs.div_ceil(addrs_per_col);
+    let height = num_steps.div_ceil(steps_per_row);
+
+    // access counts per pixel, indexed by channel: write, read, execute
+    let mut counts = vec![[0_u32; 3]; width * height];
+    for access in trace {
+        let channel = match access.kind {
+            AccessKind::Write => 0,
+            AccessKind::Read => 1,
+            AccessKind::Execute => 2,
+        };
+        let idx = access.step / steps_per_row * width + access.addr / addrs_per_col;
+        counts[idx][channel] += 1;
+    }
+
+    let mut max = [0_u32; 3];
+    for c in &counts {
+        for channel in 0..3 {
+            max[channel] = max[channel].max(c[channel]);
+        }
+    }
+
+    let scale = |count: u32, max: u32| -> u8 {
+        if count == 0 {
+            0
+        } else {
+            // keep a visible minimum so that single accesses aren't lost against the background
+            let intensity = (1.0 + count as f64).ln() / (1.0 + max as f64).ln();
+            (64.0 + 191.0 * intensity).round() as u8
+        }
+    };
+    let pixels = counts
+        .iter()
+        .map(|c| {
+            [
+                scale(c[0], max[0]),
+                scale(c[1], max[1]),
+                scale(c[2], max[2]),
+            ]
+        })
+        .collect();
+
+    Image {
+        width,
+        height,
+        pixels,
+    }
+}
+
+/// Encodes the image as a binary (P6) PPM
+pub fn encode_ppm(image: &Image) -> Vec<u8> {
+    let mut out = format!("P6\n{} {}\n255\n", image.width, image.height).into_bytes();
+    for p in &image.pixels {
+        out.extend_from_slice(p);
+    }
+    out
+}
+
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut table = [0_u32; 256];
+    for (n, entry) in table.iter_mut().enumerate() {
+        let mut c = n as u32;
+        for _ in 0..8 {
+            c = if c & 1 != 0 {
+                0xedb8_8320 ^ (c >> 1)
+            } else {
+                c >> 1
+            };
+        }
+        *entry = c;
+    }
+
+    let mut crc = 0xffff_ffff_u32;
+    for &b in bytes {
+        crc = table[((crc ^ b as u32) & 0xff) as usize] ^ (crc >> 8);
+    }
+    crc ^ 0xffff_ffff
+}
+
+fn adler32(bytes: &[u8]) -> u32 {
+    let (mut a, mut b) = (1_u32, 0_u32);
+    for &byte in bytes {
+        a = (a + byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
+
+/// Wraps the data in a zlib stream made of uncompressed deflate blocks
+fn zlib_store(data: &[u8]) -> Vec<u8> {
+    let mut out = vec![0x78, 0x01];
+    let mut blocks = data.chunks(0xffff).peekable();
+    if blocks.peek().is_none() {
+        out.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(block) = blocks.next() {
+        let is_final = blocks.peek().is_none();
+        let len = block.len() as u16;
+        out.push(is_final as u8);
+        out.extend_from_slice(&len.to_le_bytes());
+        out.extend_from_slice(&(!len).to_le_bytes());
+        out.extend_from_slice(block);
+    }
+    out.extend_from_slice(&adler32(data).to_be_bytes());
+    out
+}
+
+fn png_chunk(out: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = out.len();
+    out.extend_from_slice(kind);
+    out.extend_from_slice(data);
+    let crc = crc32(&out[start..]);
+    out.extend_from_slice(&crc.to_be_bytes());
+}
+
+/// Encodes the image as an 8-bit RGB PNG.  The image data is stored uncompressed.
+pub fn encode_png(image: &Image) -> Vec<u8> {
+    let mut out = vec![0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+
+    let mut header = Vec::new();
+    header.extend_from_slice(&(image.width as u32).to_be_bytes());
+    header.extend_from_slice(&(image.height as u32).to_be_bytes());
+    // bit depth 8, colour type RGB, default compression, filtering and no interlacing
+    header.extend_from_slice(&[8, 2, 0, 0, 0]);
+    png_chunk(&mut out, b"IHDR", &header);
+
+    let mut raw = Vec::with_capacity(image.height * (1 + 3 * image.width));
+    for row in image.pixels.chunks(image.width.max(1)) {
+        // filter type: none
+        raw.push(0);
+        for p in row {
+            raw.extend_from_slice(p);
+        }
+    }
+    png_chunk(&mut out, b"IDAT", &zlib_store(&raw));
+    png_chunk(&mut out, b"IEND", &[]);
+    out
+}
+
+/// Writes the image to the given path, as a PNG if the path ends in `.png`, otherwise as a PPM
+pub fn write_image(image: &Image, path: &Path) -> io::Result<()> {
+    let bytes = match path.extension() {
+        Some(ext) if ext == "png" => encode_png(image),
+        _ => encode_ppm(image),
+    };
+    fs::write(path, bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trace_program() {
+        // output the sum of the two inputs
+        let trace =
+            trace_program(&[3, 11, 3, 12, 1, 11, 12, 13, 4, 13, 99, 0, 0, 0], &[2, 3]).unwrap();
+        let at_step = |step| {
+            trace
+                .iter()
+                .filter(|a| a.step == step && a.kind != AccessKind::Execute)
+                .map(|a| (a.addr, a.kind))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(vec![(11, AccessKind::Write)], at_step(0));
+        assert_eq!(
+            vec![
+                (11, AccessKind::Read),
+                (12, AccessKind::Read),
+                (13, AccessKind::Write)
+            ],
+            at_step(2)
+        );
+        assert_eq!(vec![(13, AccessKind::Read)], at_step(3));
+
+        // the halt instruction is the last thing executed
+        let last = trace.last().unwrap();
+        assert_eq!(
+            (4, 10, AccessKind::Execute),
+            (last.step, last.addr, last.kind)
+        );
+    }
+
+    #[test]
+    fn test_render_heat_map() {
+        let trace = trace_program(&[1101, 2, 3, 5, 99, 0], &[]).unwrap();
+        let image = render_heat_map(&trace, &HeatMapOptions::default());
+        assert_eq!((6, 2), (image.width, image.height));
+
+        // first row executes the add and writes to 5, second row executes the halt
+        assert_eq!([0, 0, 255], image.pixels[0]);
+        assert_eq!([255, 0, 0], image.pixels[5]);
+        assert_eq!([0, 0, 0], image.pixels[6]);
+        assert_eq!([0, 0, 255], image.pixels[6 + 4]);
+    }
+
+    #[test]
+    fn test_render_downsamples() {
+        let trace = trace_program(&crate::day9::get_boost_program(), &[1]).unwrap();
+        let options = HeatMapOptions {
+            max_width: 100,
+            max_height: 50,
+        };
+        let image = render_heat_map(&trace, &options);
+        assert!(image.width <= 100 && image.width > 50);
+        assert!(image.height <= 50 && image.height > 25);
+        assert_eq!(image.width * image.height, image.pixels.len());
+    }
+
+    #[test]
+    fn test_encode_ppm() {
+        let image = Image {
+            width: 2,
+            height: 1,
+            pixels: vec![[1, 2, 3], [4, 5, 6]],
+        };
+        assert_eq!(
+            b"P6\n2 1\n255\n\x01\x02\x03\x04\x05\x06".to_vec(),
+            encode_ppm(&image)
+        );
+    }
+
+    #[test]
+    fn test_checksums() {
+        assert_eq!(0xcbf4_3926, crc32(b"123456789"));
+        assert_eq!(0x11e6_0398, adler32(b"Wikipedia"));
+    }
+
+    #[test]
+    fn test_encode_png() {
+        let image = Image {
+            width: 1,
+            height: 1,
+            pixels: vec![[255, 0, 0]],
+        };
+        let png = encode_png(&image);
+        assert_eq!(b"\x89PNG\r\n\x1a\n", &png[..8]);
+        assert_eq!(b"IHDR", &png[12..16]);
+        // the IEND chunk always has the same CRC
+        assert_eq!(b"IEND\xae\x42\x60\x82", &png[png.len() - 8..]);
+
+        // uncompressed image data: filter byte followed by the pixel
+        let idat = png.windows(4).position(|w| w == b"IDAT").unwrap();
+        assert_eq!(&[0, 255, 0, 0], &png[idat + 4 + 7..idat + 4 + 11]);
+    }
+
+    #[test]
+    fn test_write_day11_heat_map() {
+        let trace = crate::day11::trace_robot_program();
+        let image = render_heat_map(&trace, &HeatMapOptions::default());
+        let path = std::env::temp_dir().join("aoc_day11_memory.png");
+        write_image(&image, &path).unwrap();
+        let written = fs::read(&path).unwrap();
+        assert_eq!(encode_png(&image), written);
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -11,13 +11,25 @@ mod day6;
 mod day7;
 mod day8;
 mod day9;
+mod fuel_manifest;
+#[cfg(test)]
+mod intcode_conformance;
+#[cfg(test)]
+mod intcode_fuzz;
+mod intcode_optimize;
+mod intcode_visualize;
+mod orbit_render;
+mod password_rules;
+mod rocket_stages;
+mod wire_render;
+mod wire_timing;
 
 use day2::IntCodeProgram;
 
 fn main() {
     println!(
         "Day 1: The Tyranny of the Rocket Equation: {:?}",
-        day1::compute_fuel_requirement(&day1::get_modules())
+        day1::compute_fuel_requirement(day1::get_modules(), &day1::RocketEquation).unwrap()
     );
 
     // compute gravity assist parameters
@@ -49,7 +61,7 @@ fn main() {
 
     {
         use day4::*;
-        let (p1, p2) = password_search_size();
+        let (p1, p2) = password_search_size(PUZZLE_RANGE);
         println!(
             "Password search size with adjacency group >= 2: {}, == 2: {}",
             p1, p2
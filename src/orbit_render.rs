@@ -0,0 +1,457 @@
+/// Exports Day 6 orbit maps for inspection, as Graphviz DOT graphs or indented ASCII trees.  Maps
+/// with several roots are drawn as one tree after another.
+///
+/// Real orbit maps have well over a thousand bodies, mostly in long chains, so both exports can
+/// collapse chains into a single node and stop below a given depth.  A path between two bodies,
+/// such as YOU to SAN, can be highlighted.
+use crate::day6::{Body, OrbitTree};
+use std::collections::BTreeSet;
+
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct TreeOptions {
+    /// The body to start from, rather than every root in the map
+    pub root: Option<Body>,
+
+    /// Runs of at least this many bodies that each have exactly one body orbiting them are
+    /// drawn as a single node
+    pub collapse_chains: Option<usize>,
+
+    /// Bodies more than this many orbits below the starting body are left out, and counted in a
+    /// placeholder instead.  Depth is counted in bodies rather than drawn nodes, so a collapsed
+    /// chain is cut short at the limit.  The highlighted path is always drawn.
+    pub max_depth: Option<usize>,
+
+    /// Highlights the bodies on the path between these two bodies, which must lie under the
+    /// starting body
+    pub highlight: Option<(Body, Body)>,
+}
+
+/// Something drawn in the tree: a body or a collapsed chain of bodies, or a placeholder for the
+/// bodies below the depth limit
+#[derive(Debug, Clone, Eq, PartialEq)]
+enum Node<'a> {
+    Bodies {
+        /// The first and last bodies in the chain, and its length
+        first: &'a Body,
+        last: &'a Body,
+        len: usize,
+        highlighted: bool,
+    },
+    More {
+        count: usize,
+    },
+}
+
+/// A node along with where it's drawn: the node it hangs from, if any, and the prefix of the
+/// ASCII line that draws it
+struct Placed<'a> {
+    node: Node<'a>,
+    parent: Option<usize>,
+    prefix: String,
+}
+
+/// Something waiting to be laid out
+enum Pending<'a> {
+    Body(&'a Body),
+    More(usize),
+}
+
+/// Lays out the tree in the order the ASCII export draws it, each node after its parent.  This
+/// uses an explicit stack, so deep maps don't overflow the call stack.
+fn layout<'a>(tree: &'a OrbitTree, options: &'a TreeOptions) -> Result<Vec<Placed<'a>>, String> {
+    let starts = match &options.root {
+        Some(root) if !tree.contains(root) => return Err(format!("Unknown body {}", root)),
+        Some(root) => std::slice::from_ref(root),
+        None => tree.roots(),
+    };
+    let mut highlighted = BTreeSet::new();
+    // the highlighted path, and the way to it from the start, is drawn whatever the depth limit
+    let mut keep = BTreeSet::new();
+    if let Some((a, b)) = &options.highlight {
+        let path = tree
+            .transfer_path(a, b)
+            .ok_or_else(|| format!("No path from {} to {}", a, b))?;
+        highlighted.extend(path);
+        keep.extend(&highlighted);
+        let lca = tree.lca(a, b).unwrap();
+        if let Some(root) = &options.root {
+            if tree.lca(root, lca) != Some(root) {
+                return Err(format!(
+                    "The path from {} to {} is not under {}",
+                    a, b, root
+                ));
+            }
+        }
+        for start in starts {
+            keep.extend(tree.transfer_path(start, lca).into_iter().flatten());
+        }
+    }
+    // whether a body is drawn, given the depth of the body its tree is drawn from
+    let is_drawn = |body: &Body, start_depth: usize| {
+        options
+            .max_depth
+            .is_none_or(|max| tree.depth(body).unwrap() - start_depth <= max)
+            || keep.contains(body)
+    };
+
+    let mut placed = Vec::new();
+    // what to draw, the depth of the body its tree is drawn from, its parent node and the
+    // prefixes for its own line and for the lines below it
+    let mut stack: Vec<_> = starts
+        .iter()
+        .rev()
+        .map(|start| {
+            let start_depth = tree.depth(start).unwrap();
+            (
+                Pending::Body(start),
+                start_depth,
+                None,
+                String::new(),
+                String::new(),
+            )
+        })
+        .collect();
+    while let Some((pending, start_depth, parent, prefix, child_prefix)) = stack.pop() {
+        let body = match pending {
+            Pending::Body(body) => body,
+            Pending::More(count) => {
+                placed.push(Placed {
+                    node: Node::More { count },
+                    parent,
+                    prefix,
+                });
+                continue;
+            }
+        };
+
+        let mut chain = vec![body];
+        while let [only] = tree.children(chain[chain.len() - 1]) {
+            if !is_drawn(only, start_depth) {
+                break;
+            }
+            chain.push(only);
+        }
+        // only collapse as much of the chain as is long enough
+        let len = match options.collapse_chains {
+            Some(min) if chain.len() >= min => chain.len(),
+            _ => 1,
+        };
+        let last = chain[len - 1];
+        placed.push(Placed {
+            node: Node::Bodies {
+                first: body,
+                last,
+                len,
+                highlighted: chain[..len].iter().any(|b| highlighted.contains(b)),
+            },
+            parent,
+            prefix,
+        });
+        let idx = placed.len() - 1;
+
+        let mut children: Vec<Pending> = Vec::new();
+        let mut hidden = 0;
+        for child in tree.children(last) {
+            if !is_drawn(child, start_depth) {
+                hidden += tree.subtree_size(child).unwrap();
+            } else {
+                children.push(Pending::Body(child));
+            }
+        }
+        if hidden > 0 {
+            children.push(Pending::More(hidden));
+        }
+        let count = children.len();
+        for (i, child) in children.into_iter().enumerate().rev() {
+            let (connector, indent) = if i + 1 == count {
+                ("`-- ", "    ")
+            } else {
+                ("|-- ", "|   ")
+            };
+            stack.push((
+                child,
+                start_depth,
+                Some(idx),
+                child_prefix.clone() + connector,
+                child_prefix.clone() + indent,
+            ));
+        }
+    }
+    Ok(placed)
+}
+
+fn label(node: &Node) -> String {
+    match *node {
+        Node::Bodies { first, len: 1, .. } => first.to_string(),
+        Node::Bodies {
+            first, last, len, ..
+        } => format!("{} ... {} ({} bodies)", first, last, len),
+        Node::More { count: 1 } => "... (1 more body)".to_string(),
+        Node::More { count } => format!("... ({} more bodies)", count),
+    }
+}
+
+/// Renders the map as an indented tree, one node per line.  Highlighted bodies are marked with
+/// a `*`.
+pub(crate) fn render_ascii_tree(tree: &OrbitTree, options: &TreeOptions) -> Result<String, String> {
+    let mut ascii = String::new();
+    for p in layout(tree, options)? {
+        ascii += &p.prefix;
+        ascii += &label(&p.node);
+        if let Node::Bodies {
+            highlighted: true, ..
+        } = p.node
+        {
+            ascii += " *";
+        }
+        ascii += "\n";
+    }
+    Ok(ascii)
+}
+
+/// Quotes a string for use as a DOT identifier
+fn quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Renders the map as a Graphviz DOT graph, with an edge from each body to the bodies orbiting
+/// it.  Highlighted bodies, and the orbits between them, are drawn in red.
+pub(crate) fn render_dot(tree: &OrbitTree, options: &TreeOptions) -> Result<String, String> {
+    let placed = layout(tree, options)?;
+    let ids: Vec<String> = placed
+        .iter()
+        .enumerate()
+        .map(|(idx, p)| match p.node {
+            Node::Bodies { first, .. } => quote(first),
+            // placeholders don't correspond to a body, so number them instead
+            Node::More { .. } => quote(&format!("more {}", idx)),
+        })
+        .collect();
+    let is_highlighted = |idx: usize| {
+        matches!(
+            placed[idx].node,
+            Node::Bodies {
+                highlighted: true,
+                ..
+            }
+        )
+    };
+
+    let mut dot = String::from("digraph orbits {\n    rankdir=LR;\n    node [shape=box];\n");
+    for (idx, p) in placed.iter().enumerate() {
+        let mut attributes = vec![format!("label={}", quote(&label(&p.node)))];
+        if let Node::More { .. } = p.node {
+            attributes.push("shape=plaintext".to_string());
+        }
+        if is_highlighted(idx) {
+            attributes.push("color=red, penwidth=2".to_string());
+        }
+        dot += &format!("    {} [{}];\n", ids[idx], attributes.join(", "));
+    }
+    for (idx, p) in placed.iter().enumerate() {
+        if let Some(parent) = p.parent {
+            let style = if is_highlighted(parent) && is_highlighted(idx) {
+                " [color=red, penwidth=2]"
+            } else {
+                ""
+            };
+            dot += &format!("    {} -> {}{};\n", ids[parent], ids[idx], style);
+        }
+    }
+    dot += "}\n";
+    Ok(dot)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::day6::{get_local_orbits, Roots};
+
+    fn example() -> OrbitTree {
+        OrbitTree::parse("COM)B\nB)C\nC)D\nD)E\nE)F\nB)G\nG)H\nD)I\nE)J\nJ)K\nK)L\nK)YOU\nI)SAN")
+            .unwrap()
+    }
+
+    #[test]
+    fn test_ascii_tree() {
+        let expected = "\
+COM
+`-- B
+    |-- C
+    |   `-- D
+    |       |-- E
+    |       |   |-- F
+    |       |   `-- J
+    |       |       `-- K
+    |       |           |-- L
+    |       |           `-- YOU
+    |       `-- I
+    |           `-- SAN
+    `-- G
+        `-- H
+";
+        assert_eq!(
+            Ok(expected.to_string()),
+            render_ascii_tree(&example(), &TreeOptions::default())
+        );
+    }
+
+    #[test]
+    fn test_ascii_tree_options() {
+        let options = TreeOptions {
+            root: Some("D".to_string()),
+            collapse_chains: Some(2),
+            max_depth: Some(2),
+            highlight: Some(("YOU".to_string(), "SAN".to_string())),
+        };
+        let expected = "\
+D *
+|-- E *
+|   |-- F
+|   `-- J ... K (2 bodies) *
+|       |-- YOU *
+|       `-- ... (1 more body)
+`-- I ... SAN (2 bodies) *
+";
+        assert_eq!(
+            Ok(expected.to_string()),
+            render_ascii_tree(&example(), &options)
+        );
+    }
+
+    #[test]
+    fn test_depth_counts_bodies() {
+        // chains are cut short at the depth limit, rather than counting as a single level
+        let options = TreeOptions {
+            collapse_chains: Some(2),
+            max_depth: Some(2),
+            ..TreeOptions::default()
+        };
+        let expected = "\
+COM ... B (2 bodies)
+|-- C
+|   `-- ... (9 more bodies)
+`-- G
+    `-- ... (1 more body)
+";
+        assert_eq!(
+            Ok(expected.to_string()),
+            render_ascii_tree(&example(), &options)
+        );
+    }
+
+    #[test]
+    fn test_forest() {
+        let forest = OrbitTree::parse_with_roots("COM)B\nX)Y\nX)Z", &Roots::Detect).unwrap();
+        let expected = "\
+COM
+`-- B
+X
+|-- Y
+`-- Z
+";
+        assert_eq!(
+            Ok(expected.to_string()),
+            forest.render_ascii_tree(&TreeOptions::default())
+        );
+        let dot = forest.render_dot(&TreeOptions::default()).unwrap();
+        assert_eq!(3, dot.matches(" -> ").count());
+    }
+
+    #[test]
+    fn test_unknown_bodies() {
+        let options = TreeOptions {
+            root: Some("X".to_string()),
+            ..TreeOptions::default()
+        };
+        assert_eq!(
+            Err("Unknown body X".to_string()),
+            render_ascii_tree(&example(), &options)
+        );
+        let options = TreeOptions {
+            highlight: Some(("YOU".to_string(), "X".to_string())),
+            ..TreeOptions::default()
+        };
+        assert_eq!(
+            Err("No path from YOU to X".to_string()),
+            render_dot(&example(), &options)
+        );
+    }
+
+    #[test]
+    fn test_highlight_outside_root() {
+        for (root, a, b) in [("E", "YOU", "SAN"), ("D", "YOU", "G"), ("I", "F", "L")] {
+            let options = TreeOptions {
+                root: Some(root.to_string()),
+                highlight: Some((a.to_string(), b.to_string())),
+                ..TreeOptions::default()
+            };
+            assert_eq!(
+                Err(format!(
+                    "The path from {} to {} is not under {}",
+                    a, b, root
+                )),
+                render_ascii_tree(&example(), &options)
+            );
+        }
+    }
+
+    #[test]
+    fn test_dot() {
+        let options = TreeOptions {
+            collapse_chains: Some(2),
+            highlight: Some(("YOU".to_string(), "SAN".to_string())),
+            ..TreeOptions::default()
+        };
+        let dot = render_dot(&example(), &options).unwrap();
+        assert!(dot.starts_with("digraph orbits {\n"));
+        assert!(dot.ends_with("}\n"));
+
+        // collapsed chains are named after their first body
+        assert!(dot.contains("    \"COM\" [label=\"COM ... B (2 bodies)\"];\n"));
+        assert!(dot.contains("    \"C\" [label=\"C ... D (2 bodies)\", color=red, penwidth=2];\n"));
+        assert!(dot.contains("    \"F\" [label=\"F\"];\n"));
+        assert_eq!(9, dot.matches("label=").count());
+
+        let edges: Vec<&str> = dot.lines().filter(|l| l.contains(" -> ")).collect();
+        assert_eq!(
+            vec![
+                "    \"COM\" -> \"C\";",
+                "    \"C\" -> \"E\" [color=red, penwidth=2];",
+                "    \"E\" -> \"F\";",
+                "    \"E\" -> \"J\" [color=red, penwidth=2];",
+                "    \"J\" -> \"L\";",
+                "    \"J\" -> \"YOU\" [color=red, penwidth=2];",
+                "    \"C\" -> \"I\" [color=red, penwidth=2];",
+                "    \"COM\" -> \"G\";",
+            ],
+            edges
+        );
+    }
+
+    #[test]
+    fn test_dot_quotes_names() {
+        let tree = OrbitTree::parse("COM)a\"b\na\"b)c\\d").unwrap();
+        let dot = render_dot(&tree, &TreeOptions::default()).unwrap();
+        assert!(dot.contains("    \"a\\\"b\" -> \"c\\\\d\";\n"));
+    }
+
+    #[test]
+    fn test_large_map_stays_readable() {
+        let tree = get_local_orbits();
+        let full = render_ascii_tree(&tree, &TreeOptions::default()).unwrap();
+        assert_eq!(tree.subtree_size("COM"), Some(full.lines().count()));
+
+        let options = TreeOptions {
+            collapse_chains: Some(3),
+            max_depth: Some(6),
+            highlight: Some(("YOU".to_string(), "SAN".to_string())),
+            ..TreeOptions::default()
+        };
+        let ascii = render_ascii_tree(&tree, &options).unwrap();
+        assert!(ascii.lines().count() < 100);
+        assert!(ascii.contains(" *\n"));
+        let dot = render_dot(&tree, &options).unwrap();
+        assert_eq!(ascii.lines().count(), dot.matches(" -> ").count() + 1);
+    }
+}
@@ -0,0 +1,644 @@
+/// Composable rules for Day 4 passwords.
+///
+/// A password is a number, and rules look at its decimal digits.  Rules can be combined with
+/// `and`, `or` and `not`, and candidates in a range are found with a depth-first search over
+/// digits.  Rules that can reject a password from its first few digits (such as digit order or
+/// forbidden digits) prune the search, so only a small part of a large range is visited.
+use std::ops::Range;
+
+/// Rules are `Send + Sync` so that a search can be split across threads.
+pub trait PasswordRule: Send + Sync {
+    /// Whether a complete password, given as its digits, satisfies the rule
+    fn accepts(&self, digits: &[u8]) -> bool;
+
+    /// Whether some password of `len` digits starting with `prefix` could satisfy the rule.  This
+    /// is only used to prune the search, so it's always safe to return true.
+    fn may_accept_prefix(&self, _prefix: &[u8], _len: usize) -> bool {
+        true
+    }
+
+    fn and<R: PasswordRule + 'static>(self, other: R) -> And
+    where
+        Self: Sized + 'static,
+    {
+        And(vec![Box::new(self), Box::new(other)])
+    }
+
+    fn or<R: PasswordRule + 'static>(self, other: R) -> Or
+    where
+        Self: Sized + 'static,
+    {
+        Or(vec![Box::new(self), Box::new(other)])
+    }
+
+    fn not(self) -> Not
+    where
+        Self: Sized + 'static,
+    {
+        Not(Box::new(self))
+    }
+}
+
+impl<R: PasswordRule + ?Sized> PasswordRule for Box<R> {
+    fn accepts(&self, digits: &[u8]) -> bool {
+        (**self).accepts(digits)
+    }
+
+    fn may_accept_prefix(&self, prefix: &[u8], len: usize) -> bool {
+        (**self).may_accept_prefix(prefix, len)
+    }
+}
+
+/// The password has exactly this many digits
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Length(pub usize);
+
+impl PasswordRule for Length {
+    fn accepts(&self, digits: &[u8]) -> bool {
+        digits.len() == self.0
+    }
+
+    fn may_accept_prefix(&self, _prefix: &[u8], len: usize) -> bool {
+        len == self.0
+    }
+}
+
+/// The order of each pair of adjacent digits
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DigitOrder {
+    NonDecreasing,
+    Increasing,
+    NonIncreasing,
+    Decreasing,
+}
+
+impl DigitOrder {
+    fn in_order(self, a: u8, b: u8) -> bool {
+        match self {
+            DigitOrder::NonDecreasing => a <= b,
+            DigitOrder::Increasing => a < b,
+            DigitOrder::NonIncreasing => a >= b,
+            DigitOrder::Decreasing => a > b,
+        }
+    }
+}
+
+impl PasswordRule for DigitOrder {
+    fn accepts(&self, digits: &[u8]) -> bool {
+        digits.windows(2).all(|w| self.in_order(w[0], w[1]))
+    }
+
+    fn may_accept_prefix(&self, prefix: &[u8], _len: usize) -> bool {
+        self.accepts(prefix)
+    }
+}
+
+/// The lengths of the runs of repeated digits, in order
+fn group_lengths(digits: &[u8]) -> Vec<usize> {
+    let mut groups = Vec::new();
+    let mut start = 0;
+    for i in 1..=digits.len() {
+        if i == digits.len() || digits[i] != digits[start] {
+            groups.push(i - start);
+            start = i;
+        }
+    }
+    groups
+}
+
+/// Some run of repeated digits has exactly this length
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ExactGroup(pub usize);
+
+impl PasswordRule for ExactGroup {
+    fn accepts(&self, digits: &[u8]) -> bool {
+        group_lengths(digits).contains(&self.0)
+    }
+}
+
+/// Some run of repeated digits has at least this length
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct MinGroup(pub usize);
+
+impl PasswordRule for MinGroup {
+    fn accepts(&self, digits: &[u8]) -> bool {
+        group_lengths(digits).iter().any(|&g| g >= self.0)
+    }
+}
+
+/// None of these digits appear in the password
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ForbiddenDigits(pub Vec<u8>);
+
+impl PasswordRule for ForbiddenDigits {
+    fn accepts(&self, digits: &[u8]) -> bool {
+        digits.iter().all(|d| !self.0.contains(d))
+    }
+
+    fn may_accept_prefix(&self, prefix: &[u8], _len: usize) -> bool {
+        self.accepts(prefix)
+    }
+}
+
+/// The sum of the digits is between `min` and `max`, inclusive
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct DigitSum {
+    pub min: u32,
+    pub max: u32,
+}
+
+impl PasswordRule for DigitSum {
+    fn accepts(&self, digits: &[u8]) -> bool {
+        let sum: u32 = digits.iter().map(|&d| d as u32).sum();
+        self.min <= sum && sum <= self.max
+    }
+
+    fn may_accept_prefix(&self, prefix: &[u8], _len: usize) -> bool {
+        // digits only ever add to the sum
+        prefix.iter().map(|&d| d as u32).sum::<u32>() <= self.max
+    }
+}
+
+/// Every rule is satisfied
+pub struct And(pub Vec<Box<dyn PasswordRule>>);
+
+impl PasswordRule for And {
+    fn accepts(&self, digits: &[u8]) -> bool {
+        self.0.iter().all(|r| r.accepts(digits))
+    }
+
+    fn may_accept_prefix(&self, prefix: &[u8], len: usize) -> bool {
+        self.0.iter().all(|r| r.may_accept_prefix(prefix, len))
+    }
+}
+
+/// At least one rule is satisfied
+pub struct Or(pub Vec<Box<dyn PasswordRule>>);
+
+impl PasswordRule for Or {
+    fn accepts(&self, digits: &[u8]) -> bool {
+        self.0.iter().any(|r| r.accepts(digits))
+    }
+
+    fn may_accept_prefix(&self, prefix: &[u8], len: usize) -> bool {
+        self.0.iter().any(|r| r.may_accept_prefix(prefix, len))
+    }
+}
+
+/// The rule is not satisfied.  This can't prune the search.
+pub struct Not(pub Box<dyn PasswordRule>);
+
+impl PasswordRule for Not {
+    fn accepts(&self, digits: &[u8]) -> bool {
+        !self.0.accepts(digits)
+    }
+}
+
+/// The rules from the puzzle: six non-decreasing digits, with a group of at least two repeated
+/// digits, or of exactly two if `allow_larger_groups` is false
+pub fn day4_rules(allow_larger_groups: bool) -> And {
+    let group: Box<dyn PasswordRule> = if allow_larger_groups {
+        Box::new(MinGroup(2))
+    } else {
+        Box::new(ExactGroup(2))
+    };
+    And(vec![
+        Box::new(Length(6)),
+        Box::new(DigitOrder::NonDecreasing),
+        group,
+    ])
+}
+
+/// The digits of `n` in the given base, most significant first, padded with zeros to at least
+/// `width` digits
+fn digits_in_base(mut n: u64, base: u32, width: usize) -> Vec<u8> {
+    let mut digits = Vec::new();
+    while n > 0 || digits.is_empty() {
+        digits.push((n % base as u64) as u8);
+        n /= base as u64;
+    }
+    while digits.len() < width {
+        digits.push(0);
+    }
+    digits.reverse();
+    digits
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct SearchOptions {
+    /// The base the digits are written in, from 2 to 36
+    pub base: u32,
+
+    /// If set, every password has exactly this many digits, padded with leading zeros, and
+    /// numbers too large for that many digits are skipped.  Otherwise passwords are written
+    /// without leading zeros.
+    pub digits: Option<usize>,
+}
+
+impl Default for SearchOptions {
+    fn default() -> Self {
+        SearchOptions {
+            base: 10,
+            digits: None,
+        }
+    }
+}
+
+/// Lazily yields the numbers in a range that satisfy a rule, in increasing order.
+///
+/// This is a depth-first search over digits, most significant first, with an explicit stack so
+/// that it can stop after any password.  Digits whose subtree lies outside the range, or that
+/// the rule rejects as a prefix, are skipped without visiting anything below them.
+pub struct Passwords<'a> {
+    rule: &'a dyn PasswordRule,
+    range: Range<u64>,
+    base: u32,
+    padded: bool,
+
+    /// The number of digits currently being searched, and the largest to search
+    len: usize,
+    max_len: usize,
+
+    /// The digits chosen so far, and their value
+    prefix: Vec<u8>,
+    value: u128,
+
+    /// The next digit to try at each depth, up to and including the current one
+    candidates: Vec<u32>,
+}
+
+impl<'a> Passwords<'a> {
+    pub fn new(
+        range: Range<u64>,
+        rule: &'a dyn PasswordRule,
+        options: &SearchOptions,
+    ) -> Result<Passwords<'a>, String> {
+        let base = options.base;
+        if !(2..=36).contains(&base) {
+            return Err(format!("Invalid base {}", base));
+        }
+        let (min_len, max_len) = match options.digits {
+            Some(digits) => {
+                // the largest value a digit can stand for has to fit in a u64
+                let fits = (base as u128)
+                    .checked_pow(digits.saturating_sub(1) as u32)
+                    .is_some_and(|p| p <= u64::MAX as u128);
+                if digits == 0 || !fits {
+                    return Err(format!("Invalid digit count {} for base {}", digits, base));
+                }
+                (digits, digits)
+            }
+            None if range.start < range.end => (
+                digits_in_base(range.start, base, 0).len(),
+                digits_in_base(range.end - 1, base, 0).len(),
+            ),
+            None => (1, 0),
+        };
+        Ok(Passwords {
+            rule,
+            range,
+            base,
+            padded: options.digits.is_some(),
+            // the first call to `next` moves on to `min_len`
+            len: min_len - 1,
+            max_len,
+            prefix: Vec::new(),
+            value: 0,
+            candidates: Vec::new(),
+        })
+    }
+
+    /// Drops the last chosen digit
+    fn backtrack(&mut self) {
+        if self.prefix.pop().is_some() {
+            self.value /= self.base as u128;
+        }
+    }
+}
+
+impl<'a> Iterator for Passwords<'a> {
+    type Item = u64;
+
+    fn next(&mut self) -> Option<u64> {
+        loop {
+            if self.candidates.is_empty() {
+                // every password of the current length has been visited
+                if self.len >= self.max_len {
+                    return None;
+                }
+                self.len += 1;
+                // only padded passwords and single digits can start with a zero
+                let first = if self.padded || self.len == 1 { 0 } else { 1 };
+                self.candidates.push(first);
+                continue;
+            }
+
+            if self.prefix.len() == self.len {
+                let accepted = self.rule.accepts(&self.prefix);
+                let value = self.value as u64;
+                self.backtrack();
+                if accepted {
+                    return Some(value);
+                }
+                continue;
+            }
+
+            let digit = *self.candidates.last().unwrap();
+            if digit >= self.base {
+                self.candidates.pop();
+                self.backtrack();
+                continue;
+            }
+            *self.candidates.last_mut().unwrap() += 1;
+
+            // the numbers below this digit are [lo, hi)
+            let base = self.base as u128;
+            let next = self.value * base + digit as u128;
+            let remaining = base.pow((self.len - self.prefix.len() - 1) as u32);
+            let (lo, hi) = (next * remaining, (next + 1) * remaining);
+            if hi <= self.range.start as u128 || lo >= self.range.end as u128 {
+                continue;
+            }
+
+            self.prefix.push(digit as u8);
+            if self.rule.may_accept_prefix(&self.prefix, self.len) {
+                self.value = next;
+                if self.prefix.len() < self.len {
+                    self.candidates.push(0);
+                }
+            } else {
+                self.prefix.pop();
+            }
+        }
+    }
+}
+
+/// Splits a range into at most `chunks` contiguous ranges, in order, whose lengths differ by at
+/// most one
+pub fn split_range(range: Range<u64>, chunks: usize) -> Vec<Range<u64>> {
+    let len = range.end.saturating_sub(range.start);
+    let chunks = (chunks.max(1) as u64).min(len.max(1));
+    let (size, extra) = (len / chunks, len % chunks);
+    let mut start = range.start;
+    (0..chunks)
+        .map(|idx| {
+            let end = start + size + if idx < extra { 1 } else { 0 };
+            let chunk = start..end;
+            start = end;
+            chunk
+        })
+        .collect()
+}
+
+/// Lists the numbers in the range that satisfy the rule, in increasing order, searching chunks
+/// of the range on separate threads
+pub fn par_matching_passwords(
+    range: Range<u64>,
+    rule: &dyn PasswordRule,
+    options: &SearchOptions,
+    threads: usize,
+) -> Result<Vec<u64>, String> {
+    let searches = split_range(range, threads)
+        .into_iter()
+        .map(|chunk| Passwords::new(chunk, rule, options))
+        .collect::<Result<Vec<_>, String>>()?;
+    let results: Vec<Vec<u64>> = std::thread::scope(|scope| {
+        let handles: Vec<_> = searches
+            .into_iter()
+            .map(|search| scope.spawn(move || search.collect()))
+            .collect();
+        handles.into_iter().map(|h| h.join().unwrap()).collect()
+    });
+    Ok(results.concat())
+}
+
+/// Counts the numbers in the range that satisfy the rule
+pub fn count_matching(range: Range<u64>, rule: &dyn PasswordRule) -> u64 {
+    Passwords::new(range, rule, &SearchOptions::default())
+        .unwrap()
+        .count() as u64
+}
+
+/// Lists the numbers in the range that satisfy the rule, in increasing order
+pub fn matching_passwords(range: Range<u64>, rule: &dyn PasswordRule) -> Vec<u64> {
+    Passwords::new(range, rule, &SearchOptions::default())
+        .unwrap()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::day4::{count_passwords, PUZZLE_RANGE};
+    use proptest::prelude::*;
+
+    fn accepts<R: PasswordRule>(rule: &R, n: u64) -> bool {
+        rule.accepts(&digits_in_base(n, 10, 0))
+    }
+
+    #[test]
+    fn test_day4_preset() {
+        assert_eq!(910, count_matching(PUZZLE_RANGE, &day4_rules(true)));
+        assert_eq!(598, count_matching(PUZZLE_RANGE, &day4_rules(false)));
+
+        let range = 100_000..400_000;
+        let (larger, exact) = count_passwords(range.clone(), 6);
+        assert_eq!(larger, count_matching(range.clone(), &day4_rules(true)));
+        assert_eq!(exact, count_matching(range, &day4_rules(false)));
+
+        assert!(accepts(&day4_rules(false), 111122));
+        assert!(!accepts(&day4_rules(false), 123444));
+        assert!(accepts(&day4_rules(true), 123444));
+    }
+
+    #[test]
+    fn test_builtin_rules() {
+        assert!(accepts(&Length(3), 123) && !accepts(&Length(3), 1234));
+        assert!(accepts(&DigitOrder::NonDecreasing, 1123));
+        assert!(!accepts(&DigitOrder::Increasing, 1123));
+        assert!(accepts(&DigitOrder::Decreasing, 9520));
+        assert!(!accepts(&DigitOrder::NonIncreasing, 9529));
+        assert!(accepts(&ExactGroup(3), 1112333));
+        assert!(!accepts(&ExactGroup(2), 1112333));
+        assert!(accepts(&MinGroup(4), 1233334));
+        assert!(!accepts(&MinGroup(4), 1233345));
+        assert!(accepts(&ForbiddenDigits(vec![0, 7]), 123456));
+        assert!(!accepts(&ForbiddenDigits(vec![0, 7]), 123457));
+        assert!(accepts(&DigitSum { min: 10, max: 10 }, 1234));
+        assert!(!accepts(&DigitSum { min: 11, max: 20 }, 1234));
+    }
+
+    #[test]
+    fn test_combinators() {
+        let rule = Length(4)
+            .and(DigitOrder::Increasing.or(DigitOrder::Decreasing))
+            .and(ForbiddenDigits(vec![5]).not());
+        assert!(accepts(&rule, 1235));
+        assert!(accepts(&rule, 9510));
+        assert!(!accepts(&rule, 1234));
+        assert!(!accepts(&rule, 1325));
+        assert!(!accepts(&rule, 12345));
+
+        // increasing four digit numbers containing a 5 can't contain a 0, so they're choices of
+        // three digits from the other eight
+        let with_five = Length(4)
+            .and(DigitOrder::Increasing)
+            .and(ForbiddenDigits(vec![5]).not());
+        assert_eq!(56, count_matching(0..10_000, &with_five));
+    }
+
+    #[test]
+    fn test_matching_passwords() {
+        let rule = DigitOrder::Increasing.and(DigitSum { min: 6, max: 6 });
+        assert_eq!(vec![6, 15, 24, 123], matching_passwords(0..1000, &rule));
+        assert_eq!(vec![15, 24], matching_passwords(10..100, &rule));
+        let (lo, hi) = (100, 10);
+        assert_eq!(Vec::<u64>::new(), matching_passwords(lo..hi, &rule));
+    }
+
+    #[test]
+    fn test_search_is_pruned() {
+        // 18 digit non-decreasing numbers: far too many to check one by one, but few enough to
+        // find by pruning
+        let rule = Length(18)
+            .and(DigitOrder::NonDecreasing)
+            .and(ForbiddenDigits(vec![2, 3, 4, 5, 6, 7]));
+        // choosing how many of 1, 8 and 9 to use
+        assert_eq!(19 * 20 / 2, count_matching(0..u64::MAX, &rule));
+    }
+
+    #[test]
+    fn test_iterator_is_lazy_and_ordered() {
+        let rule = day4_rules(false);
+        let all = matching_passwords(PUZZLE_RANGE, &rule);
+        assert_eq!(598, all.len());
+        assert!(all.windows(2).all(|w| w[0] < w[1]));
+
+        let options = SearchOptions::default();
+        let mut passwords = Passwords::new(PUZZLE_RANGE, &rule, &options).unwrap();
+        assert_eq!(Some(all[0]), passwords.next());
+        assert_eq!(Some(all[101]), passwords.nth(100));
+        assert_eq!(Some(all[102]), passwords.next());
+        assert_eq!(Some(all[597]), passwords.last());
+
+        // stopping early doesn't search the rest of a huge range
+        let rule = DigitOrder::NonDecreasing.and(MinGroup(2));
+        let first: Vec<u64> = Passwords::new(10..u64::MAX, &rule, &options)
+            .unwrap()
+            .take(3)
+            .collect();
+        assert_eq!(vec![11, 22, 33], first);
+    }
+
+    #[test]
+    fn test_other_bases_and_lengths() {
+        let padded = SearchOptions {
+            base: 2,
+            digits: Some(4),
+        };
+        let passwords: Vec<u64> = Passwords::new(0..100, &DigitOrder::NonDecreasing, &padded)
+            .unwrap()
+            .collect();
+        assert_eq!(vec![0b0000, 0b0001, 0b0011, 0b0111, 0b1111], passwords);
+
+        // two hex digits in increasing order, neither of them zero
+        let hex = SearchOptions {
+            base: 16,
+            digits: None,
+        };
+        let rule = Length(2).and(DigitOrder::Increasing);
+        let passwords: Vec<u64> = Passwords::new(0..256, &rule, &hex).unwrap().collect();
+        assert_eq!(15 * 14 / 2, passwords.len());
+        assert_eq!(Some(&0x12), passwords.first());
+        assert_eq!(Some(&0xef), passwords.last());
+
+        // the puzzle rules with eight digits instead of six
+        let rule = Length(8).and(DigitOrder::NonDecreasing).and(MinGroup(2));
+        let (larger, _) = count_passwords(10_000_000..100_000_000, 8);
+        assert_eq!(larger, count_matching(0..u64::MAX, &rule));
+    }
+
+    #[test]
+    fn test_invalid_options() {
+        let rule = Length(1);
+        let options = |base, digits| SearchOptions { base, digits };
+        assert_eq!(
+            Some("Invalid base 1".to_string()),
+            Passwords::new(0..10, &rule, &options(1, None)).err()
+        );
+        assert!(Passwords::new(0..10, &rule, &options(37, None)).is_err());
+        assert_eq!(
+            Some("Invalid digit count 0 for base 10".to_string()),
+            Passwords::new(0..10, &rule, &options(10, Some(0))).err()
+        );
+        assert!(Passwords::new(0..10, &rule, &options(2, Some(64))).is_ok());
+        assert!(Passwords::new(0..10, &rule, &options(2, Some(65))).is_err());
+        assert!(Passwords::new(0..10, &rule, &options(36, Some(14))).is_err());
+    }
+
+    #[test]
+    fn test_split_range() {
+        assert_eq!(vec![0..4, 4..7, 7..10], split_range(0..10, 3));
+        assert_eq!(vec![5..6, 6..7], split_range(5..7, 8));
+        assert_eq!(vec![3..3], split_range(3..3, 4));
+        assert_eq!(vec![0..10], split_range(0..10, 0));
+    }
+
+    #[test]
+    fn test_parallel_search() {
+        let rule = day4_rules(true);
+        let options = SearchOptions::default();
+        let sequential = matching_passwords(0..1_000_000, &rule);
+        for threads in 1..=5 {
+            assert_eq!(
+                Ok(sequential.clone()),
+                par_matching_passwords(0..1_000_000, &rule, &options, threads)
+            );
+        }
+    }
+
+    fn rule_from(order: usize, group: usize, forbidden: Vec<u8>, max_sum: u32) -> And {
+        let order = [
+            DigitOrder::NonDecreasing,
+            DigitOrder::Increasing,
+            DigitOrder::NonIncreasing,
+            DigitOrder::Decreasing,
+        ][order];
+        order
+            .or(MinGroup(group))
+            .and(ForbiddenDigits(forbidden))
+            .and(DigitSum {
+                min: 0,
+                max: max_sum,
+            })
+    }
+
+    proptest! {
+        #[test]
+        fn prop_search_matches_brute_force(
+            order in 0_usize..4,
+            group in 1_usize..4,
+            forbidden in prop::collection::vec(0_u8..16, 0..3),
+            max_sum in 0_u32..40,
+            base in 2_u32..=16,
+            digits in prop::option::of(1_usize..6),
+            start in 0_u64..3000,
+            len in 0_u64..3000,
+        ) {
+            let rule = rule_from(order, group, forbidden, max_sum);
+            let range = start..start + len;
+            let expected: Vec<u64> = range
+                .clone()
+                .filter(|&n| {
+                    let width = digits.unwrap_or(0);
+                    let d = digits_in_base(n, base, width);
+                    // padded passwords can't have more digits than the width
+                    (digits.is_none() || d.len() == width) && rule.accepts(&d)
+                })
+                .collect();
+            let options = SearchOptions { base, digits };
+            let actual: Vec<u64> = Passwords::new(range, &rule, &options).unwrap().collect();
+            prop_assert_eq!(expected, actual);
+        }
+    }
+}
@@ -0,0 +1,252 @@
+/// Plans the fuel for a multi-stage rocket.
+///
+/// Stages are listed from the bottom of the rocket (the first to fire) to the top.  Each stage
+/// burns its fuel to lift its own modules and structure, plus everything above it: the modules,
+/// structure and fuel of every higher stage.  As in Day 1, a stage's fuel also has to lift
+/// itself, so each stage needs the full fuel cascade for the mass it lifts.
+use crate::day1::{fuel_cascade, FuelModel, Module};
+use std::convert::TryFrom;
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Stage {
+    /// Mass of the stage itself: tanks, engines and so on
+    pub structure_mass: u32,
+    pub modules: Vec<Module>,
+}
+
+impl Stage {
+    /// Mass of the stage before fuelling
+    pub fn dry_mass(&self) -> u64 {
+        self.structure_mass as u64 + self.modules.iter().map(|m| m.mass as u64).sum::<u64>()
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct StageFuel {
+    pub dry_mass: u64,
+
+    /// The stage's dry mass plus the fuelled mass of every stage above it
+    pub lifted_mass: u64,
+
+    /// Fuel for the lifted mass, including fuel for fuel
+    pub fuel: u64,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct StagePlan {
+    /// Per-stage fuel, in the same order as the stages
+    pub stages: Vec<StageFuel>,
+}
+
+impl StagePlan {
+    /// Total fuel on the launch pad
+    pub fn total_fuel(&self) -> u64 {
+        self.stages.iter().map(|s| s.fuel).sum()
+    }
+
+    /// The stage that needs the most fuel, preferring the lowest stage on ties
+    pub fn dominant_stage(&self) -> Option<usize> {
+        self.stages
+            .iter()
+            .enumerate()
+            .max_by(|(i, a), (j, b)| a.fuel.cmp(&b.fuel).then(j.cmp(i)))
+            .map(|(i, _)| i)
+    }
+}
+
+/// Computes the fuel for each stage, working down from the top.  Fails if a stage would have to
+/// lift more than the fuel model can handle.
+pub fn plan_stages<F: FuelModel + ?Sized>(
+    stages: &[Stage],
+    model: &F,
+) -> Result<StagePlan, String> {
+    let mut plan = vec![
+        StageFuel {
+            dry_mass: 0,
+            lifted_mass: 0,
+            fuel: 0
+        };
+        stages.len()
+    ];
+    let mut above = 0_u64;
+    for (idx, stage) in stages.iter().enumerate().rev() {
+        let dry_mass = stage.dry_mass();
+        let lifted_mass = dry_mass + above;
+        let mass = u32::try_from(lifted_mass)
+            .map_err(|_| format!("Stage {} lifts {}, which is too heavy", idx, lifted_mass))?;
+        let fuel = fuel_cascade(mass, model)
+            .iter()
+            .skip(1)
+            .map(|&f| f as u64)
+            .sum();
+        plan[idx] = StageFuel {
+            dry_mass,
+            lifted_mass,
+            fuel,
+        };
+        above = lifted_mass + fuel;
+    }
+    Ok(StagePlan { stages: plan })
+}
+
+/// Moving a single module from one stage to another
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct ModuleMove {
+    pub from_stage: usize,
+
+    /// Index of the module within its stage, at the time of the move
+    pub module: usize,
+    pub to_stage: usize,
+
+    /// Total fuel after the move
+    pub total_fuel: u64,
+}
+
+/// Applies a move, appending the module to its new stage
+pub fn apply_move(stages: &mut [Stage], m: &ModuleMove) {
+    let module = stages[m.from_stage].modules.remove(m.module);
+    stages[m.to_stage].modules.push(module);
+}
+
+/// Finds the single module move that saves the most fuel, if any move saves fuel at all
+pub fn best_move<F: FuelModel + ?Sized>(
+    stages: &[Stage],
+    model: &F,
+) -> Result<Option<ModuleMove>, String> {
+    let mut best_fuel = plan_stages(stages, model)?.total_fuel();
+    let mut best = None;
+    let mut candidate = stages.to_vec();
+    for from_stage in 0..stages.len() {
+        for module in 0..stages[from_stage].modules.len() {
+            for to_stage in (0..stages.len()).filter(|&s| s != from_stage) {
+                let m = ModuleMove {
+                    from_stage,
+                    module,
+                    to_stage,
+                    total_fuel: 0,
+                };
+                apply_move(&mut candidate, &m);
+                // stages that are too heavy are simply not candidates
+                if let Ok(plan) = plan_stages(&candidate, model) {
+                    if plan.total_fuel() < best_fuel {
+                        best_fuel = plan.total_fuel();
+                        best = Some(ModuleMove {
+                            total_fuel: best_fuel,
+                            ..m
+                        });
+                    }
+                }
+                candidate[from_stage] = stages[from_stage].clone();
+                candidate[to_stage] = stages[to_stage].clone();
+            }
+        }
+    }
+    Ok(best)
+}
+
+/// Suggests a sequence of module moves that reduces the total fuel, by repeatedly making the
+/// best single move until no move helps.  The moves must be applied in order.  This is a local
+/// search, so the result isn't guaranteed to be the global minimum.
+pub fn suggest_moves<F: FuelModel + ?Sized>(
+    stages: &[Stage],
+    model: &F,
+) -> Result<Vec<ModuleMove>, String> {
+    let mut stages = stages.to_vec();
+    let mut moves = Vec::new();
+    // every move strictly reduces the total fuel, so this terminates
+    while let Some(m) = best_move(&stages, model)? {
+        apply_move(&mut stages, &m);
+        moves.push(m);
+    }
+    Ok(moves)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::day1::RocketEquation;
+
+    fn stage(structure_mass: u32, masses: &[u32]) -> Stage {
+        Stage {
+            structure_mass,
+            modules: masses.iter().map(|&mass| Module { mass }).collect(),
+        }
+    }
+
+    #[test]
+    fn test_single_stage_matches_day1() {
+        // the total fuel for a single module of mass 100756, from the puzzle
+        let plan = plan_stages(&[stage(0, &[100756])], &RocketEquation).unwrap();
+        assert_eq!(50346, plan.total_fuel());
+        assert_eq!(Some(0), plan.dominant_stage());
+    }
+
+    #[test]
+    fn test_lower_stages_lift_upper_stages() {
+        // the top stage needs 966 fuel for its 1969 mass, so the bottom stage lifts
+        // 12 + 1969 + 966
+        let plan = plan_stages(&[stage(12, &[]), stage(0, &[1969])], &RocketEquation).unwrap();
+        assert_eq!(
+            StageFuel {
+                dry_mass: 1969,
+                lifted_mass: 1969,
+                fuel: 966
+            },
+            plan.stages[1]
+        );
+        assert_eq!(12, plan.stages[0].dry_mass);
+        assert_eq!(12 + 1969 + 966, plan.stages[0].lifted_mass);
+        assert_eq!(
+            fuel_cascade(2947, &RocketEquation)
+                .iter()
+                .skip(1)
+                .sum::<u32>() as u64,
+            plan.stages[0].fuel
+        );
+        assert_eq!(Some(0), plan.dominant_stage());
+        assert_eq!(plan.stages[0].fuel + plan.stages[1].fuel, plan.total_fuel());
+    }
+
+    #[test]
+    fn test_dominant_stage() {
+        assert_eq!(None, StagePlan { stages: vec![] }.dominant_stage());
+
+        // a heavy top stage can still be dominated by the stage that lifts it
+        let plan = plan_stages(
+            &[stage(100, &[]), stage(100, &[5000]), stage(0, &[])],
+            &RocketEquation,
+        )
+        .unwrap();
+        assert_eq!(0, plan.stages[2].fuel);
+        assert_eq!(Some(0), plan.dominant_stage());
+    }
+
+    #[test]
+    fn test_too_heavy() {
+        assert_eq!(
+            Err("Stage 0 lifts 4294967296, which is too heavy".to_string()),
+            plan_stages(&[stage(1, &[u32::MAX])], &RocketEquation)
+        );
+    }
+
+    #[test]
+    fn test_suggest_moves() {
+        let mut stages = vec![stage(50, &[400]), stage(50, &[3000, 20])];
+        let before = plan_stages(&stages, &RocketEquation).unwrap().total_fuel();
+        let moves = suggest_moves(&stages, &RocketEquation).unwrap();
+        assert!(!moves.is_empty());
+        assert!(moves.windows(2).all(|w| w[1].total_fuel < w[0].total_fuel));
+        assert!(moves[0].total_fuel < before);
+
+        for m in &moves {
+            apply_move(&mut stages, m);
+            assert_eq!(
+                m.total_fuel,
+                plan_stages(&stages, &RocketEquation).unwrap().total_fuel()
+            );
+        }
+        assert_eq!(None, best_move(&stages, &RocketEquation).unwrap());
+        // no modules are lost along the way
+        assert_eq!(3, stages.iter().map(|s| s.modules.len()).sum::<usize>());
+    }
+}
@@ -0,0 +1,299 @@
+/// Renders Day 3 wire layouts for review, as ASCII art or as SVG.
+///
+/// Both renderers work from the wires' segments rather than their individual cells, so the real
+/// puzzle input (hundreds of thousands of cells) is cheap to draw.  The ASCII grid is downscaled
+/// to fit the requested size, merging neighbouring cells; the SVG is scaled with its view box.
+use crate::day3::{Circuit, Junction, Metric, Path, PathPosition};
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct AsciiOptions {
+    /// Maximum width of the grid, in characters
+    pub max_width: usize,
+
+    /// Maximum height of the grid, in lines
+    pub max_height: usize,
+}
+
+impl Default for AsciiOptions {
+    fn default() -> Self {
+        AsciiOptions {
+            max_width: 120,
+            max_height: 60,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct SvgOptions {
+    /// Size of the longer side of the image, in pixels
+    pub size: usize,
+}
+
+impl Default for SvgOptions {
+    fn default() -> Self {
+        SvgOptions { size: 1000 }
+    }
+}
+
+/// Colours for the wires, reused if there are more wires than colours
+const WIRE_COLOURS: [&str; 6] = [
+    "#1f77b4", "#ff7f0e", "#2ca02c", "#9467bd", "#8c564b", "#e377c2",
+];
+
+/// The smallest and largest row and column covered by the wires, including the origin
+fn bounds(circuit: &Circuit) -> (PathPosition, PathPosition) {
+    let points = circuit
+        .wires()
+        .iter()
+        .flat_map(|w| w.segments())
+        .map(|s| s.end());
+    let (mut min_row, mut min_col, mut max_row, mut max_col) = (0, 0, 0, 0);
+    for p in points {
+        min_row = min_row.min(p.row());
+        max_row = max_row.max(p.row());
+        min_col = min_col.min(p.col());
+        max_col = max_col.max(p.col());
+    }
+    (
+        PathPosition::new(min_row, min_col),
+        PathPosition::new(max_row, max_col),
+    )
+}
+
+/// The closest junction between any two wires by the given metric
+fn closest_junction(circuit: &Circuit, metric: Metric) -> Option<&Junction> {
+    let n = circuit.wires().len();
+    (0..n)
+        .flat_map(|i| (i + 1..n).map(move |j| (i, j)))
+        .filter_map(|(i, j)| circuit.closest_crossing(i, j, metric))
+        .min_by_key(|&(junction, distance)| (distance, junction.pos))
+        .map(|(junction, _)| junction)
+}
+
+/// How strongly a character shows through when several things are drawn in the same place
+fn precedence(c: char) -> u8 {
+    match c {
+        ' ' => 0,
+        '-' | '|' => 1,
+        '+' => 2,
+        'X' => 3,
+        _ => 4,
+    }
+}
+
+fn merge(existing: char, new: char) -> char {
+    if precedence(existing) == 1 && precedence(new) == 1 && existing != new {
+        '+'
+    } else if precedence(new) > precedence(existing) {
+        new
+    } else {
+        existing
+    }
+}
+
+/// Renders the wires as ASCII art, with up at the top.  Straight runs are drawn with `-` and
+/// `|`, turns with `+`, junctions between wires with `X` and the origin with `o`.  When the
+/// layout doesn't fit, each character covers a square block of cells.
+pub fn render_ascii<P: Path>(paths: &[P], options: &AsciiOptions) -> String {
+    let circuit = Circuit::new(paths);
+    let (min, max) = bounds(&circuit);
+    let rows = (max.row() - min.row()) as usize + 1;
+    let cols = (max.col() - min.col()) as usize + 1;
+    let scale = rows
+        .div_ceil(options.max_height.max(1))
+        .max(cols.div_ceil(options.max_width.max(1)))
+        .max(1);
+    let height = rows.div_ceil(scale);
+    let width = cols.div_ceil(scale);
+
+    let mut grid = vec![vec![' '; width]; height];
+    let mut draw = |pos: PathPosition, c: char| {
+        // the top line of the grid is the highest row
+        let line = height - 1 - (pos.row() - min.row()) as usize / scale;
+        let col = (pos.col() - min.col()) as usize / scale;
+        grid[line][col] = merge(grid[line][col], c);
+    };
+
+    for wire in circuit.wires() {
+        let segments = wire.segments();
+        for (idx, segment) in segments.iter().enumerate() {
+            let (start, end) = (segment.start(), segment.end());
+            if start.row() == end.row() {
+                let (lo, hi) = (start.col().min(end.col()), start.col().max(end.col()));
+                for col in (lo..=hi).step_by(scale) {
+                    draw(PathPosition::new(start.row(), col), '-');
+                }
+                draw(PathPosition::new(start.row(), hi), '-');
+            } else {
+                let (lo, hi) = (start.row().min(end.row()), start.row().max(end.row()));
+                for row in (lo..=hi).step_by(scale) {
+                    draw(PathPosition::new(row, start.col()), '|');
+                }
+                draw(PathPosition::new(hi, start.col()), '|');
+            }
+            if idx + 1 < segments.len() {
+                draw(end, '+');
+            }
+        }
+    }
+    for junction in circuit.junctions() {
+        draw(junction.pos, 'X');
+    }
+    draw(PathPosition::new(0, 0), 'o');
+
+    let mut ascii = String::new();
+    for line in grid {
+        let line: String = line.into_iter().collect();
+        ascii += line.trim_end();
+        ascii += "\n";
+    }
+    ascii
+}
+
+/// Renders the wires as an SVG image, with each wire in its own colour.  Junctions are marked
+/// with small black circles, the closest junction by Manhattan distance with a red ring, and
+/// the closest by combined steps with a green ring.
+pub fn render_svg<P: Path>(paths: &[P], options: &SvgOptions) -> String {
+    let circuit = Circuit::new(paths);
+    let (min, max) = bounds(&circuit);
+    let extent = (max.row() - min.row()).max(max.col() - min.col()).max(1) as f64;
+    // leave a margin so that markers at the edges aren't cut off
+    let margin = extent * 0.02;
+    let radius = extent * 0.004;
+    let view_width = (max.col() - min.col()) as f64 + 2.0 * margin;
+    let view_height = (max.row() - min.row()) as f64 + 2.0 * margin;
+    let pixels_per_unit = options.size as f64 / view_width.max(view_height);
+
+    // SVG's y axis points down, so rows are negated
+    let point = |p: PathPosition| format!("{},{}", p.col(), -p.row());
+
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{:.0}\" height=\"{:.0}\" \
+         viewBox=\"{} {} {} {}\">\n",
+        view_width * pixels_per_unit,
+        view_height * pixels_per_unit,
+        min.col() as f64 - margin,
+        -max.row() as f64 - margin,
+        view_width,
+        view_height
+    );
+    svg += "<rect x=\"-100%\" y=\"-100%\" width=\"300%\" height=\"300%\" fill=\"white\"/>\n";
+
+    for (idx, wire) in circuit.wires().iter().enumerate() {
+        let mut points = vec![point(PathPosition::new(0, 0))];
+        points.extend(wire.segments().iter().map(|s| point(s.end())));
+        svg += &format!(
+            "<polyline points=\"{}\" fill=\"none\" stroke=\"{}\" stroke-width=\"1.5\" \
+             vector-effect=\"non-scaling-stroke\"><title>wire {}</title></polyline>\n",
+            points.join(" "),
+            WIRE_COLOURS[idx % WIRE_COLOURS.len()],
+            idx
+        );
+    }
+
+    for junction in circuit.junctions() {
+        svg += &format!(
+            "<circle cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"black\"/>\n",
+            junction.pos.col(),
+            -junction.pos.row(),
+            radius
+        );
+    }
+
+    let markers = [
+        (Metric::Manhattan, "red", "closest by Manhattan distance"),
+        (Metric::Steps, "green", "closest by combined steps"),
+    ];
+    for &(metric, colour, title) in &markers {
+        if let Some(junction) = closest_junction(&circuit, metric) {
+            svg += &format!(
+                "<circle cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"none\" stroke=\"{}\" \
+                 stroke-width=\"3\" vector-effect=\"non-scaling-stroke\">\
+                 <title>{} ({}, {})</title></circle>\n",
+                junction.pos.col(),
+                -junction.pos.row(),
+                radius * 4.0,
+                colour,
+                title,
+                junction.pos.row(),
+                junction.pos.col()
+            );
+        }
+    }
+
+    svg += &format!(
+        "<circle cx=\"0\" cy=\"0\" r=\"{}\" fill=\"black\"><title>origin</title></circle>\n",
+        radius * 2.0
+    );
+    svg += "</svg>\n";
+    svg
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::day3::{get_paths, parse_path, parse_wires};
+    use std::fs;
+
+    #[test]
+    fn test_render_ascii_example() {
+        let paths = parse_wires("R8,U5,L5,D3\nU7,R6,D4,L4").unwrap();
+        let expected = "\
++-----+
+|     |
+|  +--X-+
+|  |  | |
+| -X--+ |
+|  |    |
+|       |
+o-------+
+";
+        assert_eq!(expected, render_ascii(&paths, &AsciiOptions::default()));
+    }
+
+    #[test]
+    fn test_render_ascii_downscales() {
+        let paths = vec![parse_path("R100, U40").unwrap()];
+        let options = AsciiOptions {
+            max_width: 10,
+            max_height: 10,
+        };
+        let ascii = render_ascii(&paths, &options);
+        let lines: Vec<_> = ascii.lines().collect();
+        // 11 cells per character
+        assert_eq!(4, lines.len());
+        assert_eq!("o--------+", lines[3]);
+        assert!(lines[..3].iter().all(|&l| l == "         |"));
+
+        let (p1, p2) = get_paths();
+        let ascii = render_ascii(&[p1, p2], &AsciiOptions::default());
+        assert!(ascii.lines().count() <= 60);
+        assert!(ascii.lines().all(|l| l.len() <= 120));
+        assert_eq!(1, ascii.matches('o').count());
+        assert!(ascii.contains('X'));
+    }
+
+    #[test]
+    fn test_render_svg() {
+        let paths = parse_wires("R8,U5,L5,D3\nU7,R6,D4,L4").unwrap();
+        let svg = render_svg(&paths, &SvgOptions::default());
+        assert!(svg.starts_with("<svg "));
+        assert!(svg.ends_with("</svg>\n"));
+        assert_eq!(2, svg.matches("<polyline").count());
+        assert!(svg.contains("points=\"0,0 8,0 8,-5 3,-5 3,-2\""));
+        assert!(svg.contains("closest by Manhattan distance (3, 3)"));
+        assert!(svg.contains("closest by combined steps (5, 6)"));
+    }
+
+    #[test]
+    fn test_write_day3_svg() {
+        let (p1, p2) = get_paths();
+        let svg = render_svg(&[p1, p2], &SvgOptions::default());
+        assert!(svg.contains("width=\"1000\"") || svg.contains("height=\"1000\""));
+
+        let path = std::env::temp_dir().join("aoc_day3_wires.svg");
+        fs::write(&path, &svg).unwrap();
+        assert_eq!(svg, fs::read_to_string(&path).unwrap());
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,334 @@
+/// Simulates signals travelling along Day 3 wires, to find when each crossing goes live.
+///
+/// A signal enters each wire at the origin at time 0 and moves along it at the wire's speed, in
+/// cells per unit time.  Each movement of the wire can add a delay before the signal starts
+/// along it, for example to model a relay at a corner.  A crossing is live once the signals on
+/// all the wires that meet there have reached it.
+use crate::day3::{Circuit, Movement, PathPosition, WireIndex};
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct WireTiming {
+    /// Cells travelled per unit time
+    speed: f64,
+
+    /// The delay before the signal starts along each movement, by movement index.  Movements
+    /// past the end have no delay.
+    segment_delays: Vec<f64>,
+}
+
+impl WireTiming {
+    pub fn new(speed: f64, segment_delays: Vec<f64>) -> Result<WireTiming, String> {
+        if !(speed.is_finite() && speed > 0.0) {
+            return Err(format!("Invalid speed {}", speed));
+        }
+        if let Some((idx, delay)) = segment_delays
+            .iter()
+            .enumerate()
+            .find(|(_, d)| !(d.is_finite() && **d >= 0.0))
+        {
+            return Err(format!("Invalid delay {} for movement {}", delay, idx));
+        }
+        Ok(WireTiming {
+            speed,
+            segment_delays,
+        })
+    }
+
+    /// A signal moving at a constant speed, without delays
+    pub fn uniform(speed: f64) -> Result<WireTiming, String> {
+        WireTiming::new(speed, Vec::new())
+    }
+
+    fn delay(&self, movement: usize) -> f64 {
+        self.segment_delays.get(movement).cloned().unwrap_or(0.0)
+    }
+}
+
+/// When a crossing is reached by the signal on each wire that meets there
+#[derive(Debug, Clone, PartialEq)]
+pub struct CrossingTiming {
+    pub pos: PathPosition,
+
+    /// The time each wire's signal first reaches the crossing, keyed by wire index
+    pub arrivals: BTreeMap<usize, f64>,
+}
+
+impl CrossingTiming {
+    /// The time the signals on all the wires that meet here have reached the crossing
+    pub fn live_at(&self) -> f64 {
+        self.arrivals.values().cloned().fold(0.0, f64::max)
+    }
+}
+
+/// Every crossing of the wires, in the order they go live
+#[derive(Debug, Clone, PartialEq)]
+pub struct SignalTimeline {
+    /// The number of wires simulated
+    wires: usize,
+
+    pub crossings: Vec<CrossingTiming>,
+}
+
+impl SignalTimeline {
+    /// The first crossing to go live
+    pub fn first_live(&self) -> Option<&CrossingTiming> {
+        self.crossings.first()
+    }
+
+    /// The crossings that are live at the given time
+    pub fn live_by(&self, time: f64) -> impl Iterator<Item = &CrossingTiming> {
+        self.crossings
+            .iter()
+            .take_while(move |c| c.live_at() <= time)
+    }
+
+    /// Renders the timeline as CSV, with a header row and an arrival column per wire.  Wires
+    /// that don't meet at a crossing have an empty arrival.
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("row,col");
+        for wire in 1..=self.wires {
+            csv += &format!(",wire{}_arrival", wire);
+        }
+        csv += ",live_at\n";
+        for c in &self.crossings {
+            csv += &format!("{},{}", c.pos.row(), c.pos.col());
+            for wire in 0..self.wires {
+                csv += ",";
+                if let Some(arrival) = c.arrivals.get(&wire) {
+                    csv += &arrival.to_string();
+                }
+            }
+            csv += &format!(",{}\n", c.live_at());
+        }
+        csv
+    }
+}
+
+/// Looks up when a wire's signal first reaches a position, without walking the path
+struct WireClock<'a> {
+    timing: &'a WireTiming,
+    index: WireIndex,
+
+    /// The number of steps to the end of each movement
+    movement_ends: Vec<usize>,
+
+    /// The total delay before the signal starts along each movement
+    delays: Vec<f64>,
+}
+
+impl<'a> WireClock<'a> {
+    fn new(path: &Vec<Movement>, timing: &'a WireTiming) -> WireClock<'a> {
+        let mut movement_ends = Vec::with_capacity(path.len());
+        let mut delays = Vec::with_capacity(path.len());
+        let (mut steps, mut delay) = (0, 0.0);
+        for (idx, movement) in path.iter().enumerate() {
+            steps += movement.amount();
+            delay += timing.delay(idx);
+            movement_ends.push(steps);
+            delays.push(delay);
+        }
+        WireClock {
+            timing,
+            index: WireIndex::new(path),
+            movement_ends,
+            delays,
+        }
+    }
+
+    /// The time the signal first reaches the position, if the wire visits it
+    fn arrival(&self, pos: PathPosition) -> Option<f64> {
+        let step = self.index.first_step(pos)?;
+        let movement = self.movement_ends.partition_point(|&end| end < step);
+        Some(self.delays[movement] + step as f64 / self.timing.speed)
+    }
+}
+
+/// Simulates signals along every wire, each with its own timing, and returns the crossings in
+/// the order they go live.  Crossings that go live at the same time are ordered by position.
+pub fn simulate(paths: &[Vec<Movement>], timings: &[WireTiming]) -> Result<SignalTimeline, String> {
+    if paths.len() != timings.len() {
+        return Err(format!(
+            "Expected a timing for each of the {} wires, got {}",
+            paths.len(),
+            timings.len()
+        ));
+    }
+    let clocks: Vec<WireClock> = paths
+        .iter()
+        .zip(timings)
+        .map(|(path, timing)| WireClock::new(path, timing))
+        .collect();
+
+    let mut timeline: Vec<CrossingTiming> = Circuit::new(paths)
+        .junctions()
+        .iter()
+        .map(|junction| CrossingTiming {
+            pos: junction.pos,
+            arrivals: junction
+                .steps
+                .keys()
+                .map(|&wire| (wire, clocks[wire].arrival(junction.pos).unwrap()))
+                .collect(),
+        })
+        .collect();
+    timeline.sort_by(|a, b| {
+        a.live_at()
+            .partial_cmp(&b.live_at())
+            .unwrap()
+            .then(a.pos.cmp(&b.pos))
+    });
+    Ok(SignalTimeline {
+        wires: paths.len(),
+        crossings: timeline,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::day3::{get_paths, parse_path};
+
+    fn example() -> Vec<Vec<Movement>> {
+        vec![
+            parse_path("R8,U5,L5,D3").unwrap(),
+            parse_path("U7,R6,D4,L4").unwrap(),
+        ]
+    }
+
+    fn arrivals(c: &CrossingTiming) -> Vec<f64> {
+        c.arrivals.values().cloned().collect()
+    }
+
+    #[test]
+    fn test_uniform_speed() {
+        // at equal speeds, arrival times are step counts, and a crossing is live once the wire
+        // that takes more steps reaches it
+        let paths = example();
+        let unit = WireTiming::uniform(1.0).unwrap();
+        let timeline = simulate(&paths, &[unit.clone(), unit.clone()]).unwrap();
+        assert_eq!(
+            vec![
+                CrossingTiming {
+                    pos: PathPosition::new(5, 6),
+                    arrivals: vec![(0, 15.0), (1, 15.0)].into_iter().collect()
+                },
+                CrossingTiming {
+                    pos: PathPosition::new(3, 3),
+                    arrivals: vec![(0, 20.0), (1, 20.0)].into_iter().collect()
+                },
+            ],
+            timeline.crossings
+        );
+
+        let slow = WireTiming::uniform(0.5).unwrap();
+        let timeline = simulate(&paths, &[unit, slow]).unwrap();
+        assert_eq!(vec![15.0, 30.0], arrivals(timeline.first_live().unwrap()));
+        assert_eq!(30.0, timeline.first_live().unwrap().live_at());
+    }
+
+    #[test]
+    fn test_segment_delays() {
+        // a long delay before the first wire turns down makes (5, 6) go live first
+        let paths = example();
+        let unit = WireTiming::uniform(1.0).unwrap();
+        let delayed = WireTiming::new(1.0, vec![0.0, 0.0, 0.0, 10.0]).unwrap();
+        let timeline = simulate(&paths, &[delayed, unit.clone()]).unwrap();
+        assert_eq!(PathPosition::new(5, 6), timeline.first_live().unwrap().pos);
+        assert_eq!(vec![30.0, 20.0], arrivals(&timeline.crossings[1]));
+        assert_eq!(1, timeline.live_by(29.0).count());
+        assert_eq!(2, timeline.live_by(30.0).count());
+
+        // delaying the first movement of the second wire delays everything on it
+        let late = WireTiming::new(2.0, vec![5.0]).unwrap();
+        let timeline = simulate(&paths, &[unit, late]).unwrap();
+        assert_eq!(vec![15.0, 12.5], arrivals(timeline.first_live().unwrap()));
+    }
+
+    #[test]
+    fn test_many_wires() {
+        // a third wire meets the other two at (3, 3), and only the first elsewhere
+        let mut paths = example();
+        paths.push(parse_path("R3,U3").unwrap());
+        let unit = WireTiming::uniform(1.0).unwrap();
+        let fast = WireTiming::uniform(3.0).unwrap();
+        let timeline = simulate(&paths, &[unit.clone(), unit, fast]).unwrap();
+        let crossings: Vec<_> = timeline
+            .crossings
+            .iter()
+            .map(|c| (c.pos, arrivals(c), c.live_at()))
+            .collect();
+        assert_eq!(
+            vec![
+                (PathPosition::new(0, 1), vec![1.0, 1.0 / 3.0], 1.0),
+                (PathPosition::new(0, 2), vec![2.0, 2.0 / 3.0], 2.0),
+                (PathPosition::new(0, 3), vec![3.0, 1.0], 3.0),
+                (PathPosition::new(5, 6), vec![15.0, 15.0], 15.0),
+                (PathPosition::new(3, 3), vec![20.0, 20.0, 2.0], 20.0),
+                (PathPosition::new(2, 3), vec![21.0, 5.0 / 3.0], 21.0),
+            ],
+            crossings
+        );
+        assert_eq!(
+            vec![&0, &2],
+            timeline.crossings[0].arrivals.keys().collect::<Vec<_>>()
+        );
+
+        assert_eq!(
+            Err("Expected a timing for each of the 3 wires, got 1".to_string()),
+            simulate(&paths, &[WireTiming::uniform(1.0).unwrap()])
+        );
+    }
+
+    #[test]
+    fn test_invalid_timing() {
+        assert!(WireTiming::uniform(0.0).is_err());
+        assert!(WireTiming::uniform(-1.0).is_err());
+        assert!(WireTiming::uniform(f64::NAN).is_err());
+        assert_eq!(
+            Err("Invalid delay -1 for movement 1".to_string()),
+            WireTiming::new(1.0, vec![0.0, -1.0])
+        );
+    }
+
+    #[test]
+    fn test_timeline_csv() {
+        let mut paths = example();
+        let unit = WireTiming::uniform(1.0).unwrap();
+        let slow = WireTiming::uniform(0.5).unwrap();
+        assert_eq!(
+            "row,col,wire1_arrival,wire2_arrival,live_at\n5,6,15,30,30\n3,3,20,40,40\n",
+            simulate(&paths, &[unit.clone(), slow.clone()])
+                .unwrap()
+                .to_csv()
+        );
+
+        paths.push(parse_path("L3").unwrap());
+        assert_eq!(
+            "row,col,wire1_arrival,wire2_arrival,wire3_arrival,live_at\n\
+             5,6,15,30,,30\n3,3,20,40,,40\n",
+            simulate(&paths, &[unit.clone(), slow, unit])
+                .unwrap()
+                .to_csv()
+        );
+    }
+
+    #[test]
+    fn test_input_matches_step_counts() {
+        let (p1, p2) = get_paths();
+        let unit = WireTiming::uniform(1.0).unwrap();
+        let paths = vec![p1, p2];
+        let timeline = simulate(&paths, &[unit.clone(), unit]).unwrap();
+        let circuit = Circuit::new(&paths);
+        assert_eq!(circuit.junctions().len(), timeline.crossings.len());
+        for junction in circuit.junctions() {
+            let c = timeline.crossings.iter().find(|c| c.pos == junction.pos);
+            let steps: Vec<f64> = junction.steps.values().map(|&s| s as f64).collect();
+            assert_eq!(Some(steps), c.map(arrivals));
+        }
+        assert!(timeline
+            .crossings
+            .windows(2)
+            .all(|w| w[0].live_at() <= w[1].live_at()));
+    }
+}